@@ -1,37 +1,82 @@
-use crate::discord::{commands::PunishmentType, embed::profembed, punishment::*, thread::*};
+use crate::discord::{commands::{Capability, PunishmentType}, duration::Length, embed::{embed_length, history_pages, profembed}, permission::*, punishment::*, appeal::{AppealId, appeal_button}, profile::{page_buttons, profile_buttons}, responder::Responder, thread::*};
 use polodb_core::{CollectionT, Database, IndexModel, bson::doc};
 use serde::{Deserialize, Serialize};
 use serenity::{
     all::{CommandInteraction, PartialMember, Role, User},
-    builder::{CreateInteractionResponse, CreateInteractionResponseMessage},
-    model::{Timestamp, id::{ChannelId, GuildId}},     prelude::*,
+    builder::{CreateInteractionResponse, CreateInteractionResponseMessage, CreateMessage, EditInteractionResponse},
+    model::{Timestamp, id::{ChannelId, GuildId, MessageId, UserId}},     prelude::*,
 };
-use std::collections::BTreeMap;
-use tokio::{task::JoinHandle, time::{sleep, Duration}, sync::mpsc::{Sender,Receiver}};
+use std::{collections::{BTreeMap, BTreeSet}, sync::Arc};
+use tokio::{time::sleep, sync::{mpsc::Receiver, oneshot}};
 
+mod appeal;
+mod archive;
+mod automod;
+mod config;
+mod decay;
+mod escalation;
+mod evidence;
+mod external;
+#[cfg(test)]
+mod fixtures;
+mod points;
+mod scheduler;
+pub use appeal::{Appeal, AppealState};
+pub use automod::AutoModHit;
+pub use config::{ConfigChanges, GuildConfig};
+pub use escalation::{AutoAction, EscalationRule};
+pub use evidence::{AttachmentEvidence, Evidence, MessageSnapshot};
+pub use external::ExternalChange;
+pub use points::point_total;
+pub use scheduler::{Job, JobKind};
+
+// Users holding an active mute or timeout, so member joins can be checked without a database query.
+pub type PunishedSet = Arc<RwLock<BTreeMap<(GuildId, i64), bool>>>; //GuildID, UserID -> Whether one of them is a timeout
+// Bans and timeouts the bot issued or lifted itself, so their events can be skipped when the audit log lags behind.
+pub type OwnActions = Arc<RwLock<BTreeMap<(GuildId, i64, PunishmentType), Timestamp>>>; //GuildID, UserID, Ban or Timeout -> When
+// Seconds an own action is remembered, the audit log lookup gives up well before.
+pub const OWN_ACTION_TTL: i64 = 60;
+// Role permissions per guild, read by ClientHandler to authorize commands before they reach DBHandler.
+pub type RoleCache = Arc<RwLock<BTreeMap<GuildId, Vec<RolePermission>>>>;
 
 pub struct DBHandler {
     database: BTreeMap<GuildId, GuildDB>,
     threadlog: BTreeMap<GuildId, (ChannelId, ChannelId)>, //Log Channel, Notifier Channel
     context: Option<serenity::prelude::Context>,
     receiver: Receiver<DBRequest>,
-    sender: Sender<DBRequest>,
-    active_temps: BTreeMap<i64, (GuildId, Temporary, JoinHandle<()>)>, //UserID, (GuildID, Temporary)
+    jobs: BTreeSet<(i64, GuildId, String)>, //Due, GuildID, JobID
+    punished: PunishedSet,
+    own_actions: OwnActions,
+    roles: RoleCache,
 }
 
 impl DBHandler {
-    pub fn new(receiver: Receiver<DBRequest>, sender: Sender<DBRequest>) -> Self {
+    pub fn new(receiver: Receiver<DBRequest>, punished: PunishedSet, own_actions: OwnActions, roles: RoleCache) -> Self {
         DBHandler {
             database: BTreeMap::new(),
             threadlog: BTreeMap::new(),
             context: None,
             receiver,
-            sender,
-            active_temps: BTreeMap::new(),
+            jobs: BTreeSet::new(),
+            punished,
+            own_actions,
+            roles,
         }
     }
     pub async fn process_requests(&mut self) {
-        while let Some(request) = self.receiver.recv().await {
+        loop {
+            // Single timer loop, requests are handled as they come and due jobs run whenever the wait elapses.
+            let wait = self.next_wait();
+            let request = tokio::select! {
+                request = self.receiver.recv() => match request {
+                    Some(request) => request,
+                    None => break,
+                },
+                _ = sleep(wait) => {
+                    self.run_due_jobs().await;
+                    continue;
+                }
+            };
             match request.request_type {
                 DBRequestType::GiveContext => {
                     if let Some(context) = request.context {
@@ -41,63 +86,7 @@ impl DBHandler {
                 DBRequestType::Build => {
                     if let Some((guild, logs)) = request.threadlog {
                         self.threadlog.insert(guild, logs);
-
-                        if let Err(e) = std::fs::create_dir_all("server/databases") {
-                            eprintln!("Failed to create database folder: {}", e);
-                            continue;
-                        }
-
-                        let db_path = format!("server/databases/{}.db", guild);
-                        // Hang can occur here if improper drop, application closing needs to be handled eventually.
-                        match Database::open_path(&db_path) {
-                            Ok(db) => {
-                                let profilecol = db.collection::<Profile>("Profile");
-                                let tempcol = db.collection::<Temporary>("Temporary");
-                                let rolecol = db.collection::<RolePermission>("RolePermission");
-
-                                // Store with Bitwise ! duration to get the most recent punishment at the top
-                                // ASC is the only working order (1)
-                                if let Err(e) = profilecol.create_index(IndexModel {
-                                    keys: doc! {
-                                        "negdur": 1,
-                                    },
-                                    options: None,
-                                }) {
-                                    eprintln!(
-                                        "Failed to create index for Profile collection in guild {}: {}",
-                                        guild, e
-                                    );
-                                }
-
-                                if let Err(e) = tempcol.create_index(IndexModel {
-                                    keys: doc! {
-                                        "negdur": 1,
-                                    },
-                                    options: None,
-                                }) {
-                                    eprintln!(
-                                        "Failed to create index for Temporary collection in guild {}: {}",
-                                        guild, e
-                                    );
-                                }
-                                
-                                self.database.insert(
-                                    guild,
-                                    GuildDB {
-                                        db,
-                                        profilecol,
-                                        tempcol,
-                                        rolecol,
-                                    },
-                                );
-                            }
-                            Err(e) => {
-                                eprintln!(
-                                    "Failed to initialize database for guild {}: {}",
-                                    guild, e
-                                );
-                            }
-                        }
+                        self.open_guild(guild).await;
                     }
                 }
                 DBRequestType::FetchProfile => {
@@ -106,27 +95,41 @@ impl DBHandler {
                             Command::FetchProfile {command, 
                                                     target, 
                                                     targetguild,
-                                                    invoker, .. } => {  
+                                                    invoker,
+                                                    page, .. } => {  
                                 if let Some(userprofile) = self
                                     .get_profile(target.0.id.get() as i64, &targetguild)
                                     .await
                                 {
+                                    let summary = profembed(
+                                        &invoker,
+                                        &target,
+                                        &userprofile.punishments,
+                                        &userprofile.automod,
+                                        userprofile.quarantined,
+                                        &self.get_config(&targetguild),
+                                    )
+                                    .await;
+                                    // Both embeds share one message, so the page gets what the summary leaves.
+                                    let pages = history_pages(&userprofile.punishments, embed_length(&summary));
+                                    let page = page.min(pages.len().saturating_sub(1));
+                                    let total = pages.len();
+                                    let mut message = CreateInteractionResponseMessage::new()
+                                        .embed(summary)
+                                        .ephemeral(true);
+                                    if let Some(history) = pages.into_iter().nth(page) {
+                                        message = message.add_embed(history);
+                                    }
+                                    if total > 1 {
+                                        message = message.components(vec![page_buttons(targetguild, userprofile.user_id, page, total)]);
+                                    }
+                                    // Prev and Next replace the page they were pressed on.
+                                    let response = match command {
+                                        Responder::Component(_) => CreateInteractionResponse::UpdateMessage(message),
+                                        _ => CreateInteractionResponse::Message(message),
+                                    };
                                     command
-                                        .create_response(
-                                            &ctx.http,
-                                            CreateInteractionResponse::Message(
-                                                CreateInteractionResponseMessage::new()
-                                                    .embed(
-                                                        profembed(
-                                                            &invoker,
-                                                            &target,
-                                                            &userprofile.punishments,
-                                                        )
-                                                        .await,
-                                                    )
-                                                    .ephemeral(true),
-                                            ),
-                                        )
+                                        .create_response(&ctx.http, response)
                                         .await
                                         .expect("Failed to send response");
                                 }
@@ -138,195 +141,553 @@ impl DBHandler {
                     }
                 }
                 DBRequestType::Punishment => {
+                    if let (Some(cmd), Some(ctx)) = (request.command, request.context) {
+                        self.handle_punishment(cmd, ctx).await;
+                    }
+                }
+                DBRequestType::CommandPermissionUpdate => {
                     if let (Some(cmd), Some(ctx)) = (request.command, request.context) {
                         match cmd {
-                            Command::PunishAdd {command, 
-                                                target, 
-                                                targetguild,
-                                                invoker,
-                                                ptype,
-                                                reason,
-                                                length, .. } => {  
-                                let end = Timestamp::from_unix_timestamp(Timestamp::now().unix_timestamp() + length.unwrap_or(-Timestamp::now().unix_timestamp()));
-                                let idkey = target.0.id.get() as i64;
-                                match end {
-                                    Ok(end) => {
-                                        if let Some(punishment) = self.process_punishment( idkey,  
-                                            &invoker,
-                                            &target,
-                                            ptype.clone(),
-                                            reason,
-                                            (Timestamp::now(), end),
-                                            &targetguild,
-                                            &ctx).await {
-                                                 apply_punishment(&ctx, targetguild, &punishment, &target.0)
-                                                    .await
-                                                    .expect("Failed to apply punishment");
-
-                                                command
-                                                    .create_response(
-                                                        &ctx.http,
-                                                        CreateInteractionResponse::Message(
-                                                            CreateInteractionResponseMessage::new()
-                                                                .content(format!("Added {:?} punishment to <@{}>.", ptype, idkey))
-                                                                .ephemeral(true),
-                                                        ),
-                                                    )
-                                                    .await
-                                                    .expect("Failed to send response");
-
-                                                if length.is_some() {
-                                                    self.add_temporary(command, idkey, target, targetguild, invoker, Temporary {
-                                                        user_id: idkey,
-                                                        punishment,
-                                                        negdur: !Timestamp::now().unix_timestamp(),
-                                                    }).await;
-                                                }                       
-                                            }
-                                            println!("Added punishment {:?} to user {}.", ptype, idkey);    
-                                    },
-                                    Err(e) => {
-                                        command
-                                            .create_response(
-                                                &ctx.http,
-                                                CreateInteractionResponse::Message(
-                                                    CreateInteractionResponseMessage::new()
-                                                        .content(format!("Invalid timestamp conversion: {}", e))
-                                                        .ephemeral(true),
-                                                ),
-                                            )
-                                            .await
-                                            .expect("Failed to send response");
-                                        continue;
-                                    }
-                                }
-                                
-                            }
-                            Command::PunishEdit {command, 
+                            Command::RoleAdjust {command, 
                                                 target, 
                                                 targetguild,
                                                 invoker,
-                                                id,
-                                                latest,
-                                                length,
-                                                reason, .. } => {  
-                                let idkey = target.0.id.get() as i64;
-                                if let Some(mut userprofile) = self
-                                    .get_profile(idkey, &targetguild)
-                                    .await
+                                                allow,
+                                                capability } => {  
+                                println!("{} set {:?} permission for role {} to {} in guild {}.", invoker.name, capability, target.id, allow, targetguild);
+                                let content = if self
+                                    .set_roleperm(target.id.get() as i64, capability, allow, &targetguild)
+                                    .await 
                                 {
-                                    self.update_profile(&userprofile, &targetguild, &target, &invoker, &ctx).await;
-
-                                    command
-                                            .create_response(
-                                                &ctx.http,
-                                                CreateInteractionResponse::Message(
-                                                    CreateInteractionResponseMessage::new()
-                                                        .content(format!("Edited punishment for <@{}>.", idkey))
-                                                        .ephemeral(true),
-                                                ),
-                                            )
-                                            .await
-                                            .expect("Failed to send response");
-
-                                    if self.active_temps.contains_key(&idkey) && length.is_some() {
-                                        if let Some((_,record,_)) = self.active_temps.get_mut(&idkey) {
-                                            userprofile.edit_punishment(id, latest, length, reason, Some(record));
-                                            let record_clone = record.clone();
-                                            self.remove_temporary(idkey, &targetguild).await;
-                                            self.add_temporary(command,idkey, target, targetguild, invoker, record_clone, ).await;
-                                        }
-                                    } else {
-                                        userprofile.edit_punishment(id, latest, length, reason, None);
-                                    }
-                                    
+                                    let roleperms = self.list_roleperms(&targetguild).await;
+                                    self.roles.write().await.insert(targetguild, roleperms.clone());
+                                    permission_table(&roleperms)
                                 } else {
-                                        command
-                                            .create_response(
-                                                &ctx.http,
-                                                CreateInteractionResponse::Message(
-                                                    CreateInteractionResponseMessage::new()
-                                                        .content(format!("<@{}> lacks any punishment history.", idkey))
-                                                        .ephemeral(true),
-                                                ),
-                                            )
-                                            .await
-                                            .expect("Failed to send response");
+                                    format!("Failed to update permissions for <@&{}>.", target.id)
+                                };
+
+                                command
+                                    .create_response(
+                                        &ctx.http,
+                                        CreateInteractionResponse::Message(
+                                            CreateInteractionResponseMessage::new()
+                                                .content(content)
+                                                .ephemeral(true),
+                                        ),
+                                    )
+                                    .await
+                                    .expect("Failed to send response");
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                DBRequestType::Config => {
+                    match (request.command, request.context) {
+                        (Some(Command::GetConfig { targetguild, reply }), _) => {
+                            self.open_guild(targetguild).await;
+                            if reply.send(self.get_config(&targetguild)).is_err() {
+                                eprintln!("Config requester for guild {} went away", targetguild);
+                            }
+                        }
+                        (Some(Command::SaveConfig { targetguild, config }), _) => {
+                            self.save_config(&targetguild, &config);
+                        }
+                        (Some(Command::ModConfigView { command, targetguild }), Some(ctx)) => {
+                            command
+                                .create_response(
+                                    &ctx.http,
+                                    CreateInteractionResponse::Message(
+                                        CreateInteractionResponseMessage::new()
+                                            .content(self.get_config(&targetguild).describe())
+                                            .ephemeral(true),
+                                    ),
+                                )
+                                .await
+                                .expect("Failed to send response");
+                        }
+                        (Some(Command::ModConfigSet { command, targetguild, invoker, changes }), Some(ctx)) => {
+                            let mut config = self.get_config(&targetguild);
+                            let content = match config.apply(changes) {
+                                Ok(()) => {
+                                    self.save_config(&targetguild, &config);
+                                    println!("{} updated the modbot config in guild {}.", invoker.name, targetguild);
+                                    format!("Updated configuration.\n\n{}", config.describe())
                                 }
+                                Err(e) => e,
+                            };
+                            command
+                                .create_response(
+                                    &ctx.http,
+                                    CreateInteractionResponse::Message(
+                                        CreateInteractionResponseMessage::new()
+                                            .content(content)
+                                            .ephemeral(true),
+                                    ),
+                                )
+                                .await
+                                .expect("Failed to send response");
+                        }
+                        _ => {}
+                    }
+                }
+                DBRequestType::Appeal => {
+                    if let (Some(cmd), Some(ctx)) = (request.command, request.context) {
+                        match cmd {
+                            Command::AppealSubmit { command, targetguild, user, pid, statement } => {
+                                self.submit_appeal(command, targetguild, user, pid, statement, &ctx).await;
                             }
-                            Command::PunishRemove {command, 
-                                                    target, 
-                                                    targetguild,
-                                                    invoker,
-                                                    id,
-                                                    latest,
-                                                    silent,  .. } => {  
-                                let idkey = target.0.id.get() as i64;
-                                if let Some(mut userprofile) = self
-                                    .get_profile(idkey, &targetguild)
-                                    .await
-                                {
-                                    if self.active_temps.contains_key(&idkey) {
-                                        self.remove_temporary(idkey, &targetguild).await;
-                                    }
-
-                                    if let Some(removed) = userprofile.remove_punishment(id, latest) {
-                                        remove_punishment(&ctx, targetguild, &removed, &target.0)
-                                            .await
-                                            .expect("Failed to remove punishment");
-                                    }
-
-                                    self.update_profile(&userprofile, &targetguild, &target, &invoker, &ctx).await;
-                                    if !silent {
-                                        command.
-                                            create_response(
-                                                &ctx.http,
-                                                CreateInteractionResponse::Message(
-                                                    CreateInteractionResponseMessage::new()
-                                                        .content(format!("Removed punishment for <@{}>.", target.0.id))
-                                                        .ephemeral(true),
-                                                ),
-                                            )
-                                            .await
-                                            .expect("Failed to send response");                                   
+                            Command::AppealDecide { command, id, target, decision, length } => {
+                                self.decide_appeal(command, id, target, decision, length, ctx).await;
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                DBRequestType::Evidence => {
+                    if let (Some(Command::EvidenceGet { command, targetguild, target, id }), Some(ctx)) = (request.command, request.context) {
+                        self.get_evidence(command, targetguild, target, id, &ctx).await;
+                    }
+                }
+                DBRequestType::Escalation => {
+                    if let (Some(cmd), Some(ctx)) = (request.command, request.context) {
+                        let (command, content) = match cmd {
+                            Command::EscalationList { command, targetguild } => {
+                                let rules = self.list_escalations(&targetguild);
+                                let content = if rules.is_empty() {
+                                    "No escalation rules.".to_string()
+                                } else {
+                                    rules.iter().map(EscalationRule::describe).collect::<Vec<String>>().join("\n")
+                                };
+                                (command, content)
+                            }
+                            Command::EscalationAdd { command, targetguild, invoker, rule } => {
+                                let content = match self.add_escalation(&targetguild, rule) {
+                                    Some(rule) => {
+                                        println!("{} added escalation rule {} in guild {}.", invoker.name, rule.rule_id, targetguild);
+                                        format!("Added escalation rule: {}", rule.describe())
                                     }
+                                    None => "Failed to save the escalation rule.".to_string(),
+                                };
+                                (command, content)
+                            }
+                            Command::EscalationRemove { command, targetguild, id } => {
+                                let content = if self.remove_escalation(&targetguild, &id) {
+                                    format!("Removed escalation rule `{}`.", id)
                                 } else {
-                                        command
-                                            .create_response(
-                                                &ctx.http,
-                                                CreateInteractionResponse::Message(
-                                                    CreateInteractionResponseMessage::new()
-                                                        .content(format!("<@{}> lacks any punishment history.", target.0.id))
-                                                        .ephemeral(true),
-                                                ),
-                                            )
-                                            .await
-                                            .expect("Failed to send response");
-                                }
+                                    format!("No escalation rule with ID `{}`.", id)
+                                };
+                                (command, content)
                             }
-                            _ => { 
+                            _ => continue,
+                        };
+                        command
+                            .create_response(
+                                &ctx.http,
+                                CreateInteractionResponse::Message(
+                                    CreateInteractionResponseMessage::new()
+                                        .content(content)
+                                        .ephemeral(true),
+                                ),
+                            )
+                            .await
+                            .expect("Failed to send response");
+                    }
+                }
+                DBRequestType::Rejoin => {
+                    if let (Some(Command::Rejoin { targetguild, target }), Some(ctx)) = (request.command, request.context) {
+                        self.reapply_punishments(targetguild, target, &ctx).await;
+                    }
+                }
+                DBRequestType::External => {
+                    if let (Some(Command::External { targetguild, target, change, moderator, reason }), Some(ctx)) = (request.command, request.context) {
+                        self.ingest_external(targetguild, target, change, moderator, reason, &ctx).await;
+                    }
+                }
+                DBRequestType::AutoMod => {
+                    if let (Some(cmd), Some(ctx)) = (request.command, request.context) {
+                        match cmd {
+                            Command::AutoModHit { targetguild, target, hit } => {
+                                self.record_automod(targetguild, target, hit, &ctx).await;
+                            }
+                            Command::Quarantine { targetguild, target, quarantined } => {
+                                self.set_quarantine(targetguild, target, quarantined, &ctx).await;
                             }
+                            _ => {}
                         }
                     }
                 }
-                DBRequestType::CommandPermissionUpdate => {
+                DBRequestType::Schedule => {
                     if let (Some(cmd), Some(ctx)) = (request.command, request.context) {
-                        match cmd {
-                            Command::RoleAdjust {command, 
-                                                target, 
-                                                targetguild,
-                                                invoker, .. } => {  
-                                if let Some(roleperm) = self
-                                    .get_roleperm(target.id.get() as i64, &targetguild)
-                                    .await 
-                                {
+                        let (command, content) = match cmd {
+                            Command::ScheduleList { command, targetguild } => {
+                                // The decay sweep is internal and always pending, it would only be noise here.
+                                let jobs = self.list_jobs(&targetguild)
+                                    .into_iter()
+                                    .filter(|job| !matches!(job.kind, JobKind::DecaySweep))
+                                    .collect::<Vec<Job>>();
+                                let content = if jobs.is_empty() {
+                                    "No pending jobs.".to_string()
+                                } else {
+                                    // Keep the listing inside a single message.
+                                    let mut content = jobs.iter().take(20).map(Job::describe).collect::<Vec<String>>().join("\n");
+                                    if jobs.len() > 20 {
+                                        content.push_str(&format!("\n...and {} more.", jobs.len() - 20));
+                                    }
+                                    content
+                                };
+                                (command, content)
+                            }
+                            Command::ScheduleCancel { command, targetguild, id } => {
+                                let content = match self.get_job(targetguild, &id) {
+                                    // Expiries belong to their punishment, cancelling one would leave it in place while the record still shows its end.
+                                    Some(Job { kind: JobKind::Expire { user_id, punishment_id }, .. }) => {
+                                        format!("Job `{}` expires punishment {} of <@{}>, change it with /punish edit instead.", id, punishment_id, user_id)
+                                    }
+                                    // Without the sweep, points stop fading and records stop expiring until the next restart.
+                                    Some(Job { kind: JobKind::DecaySweep, .. }) => {
+                                        format!("Job `{}` is the decay sweep and can't be cancelled, set the decay windows with /modconfig set instead.", id)
+                                    }
+                                    Some(_) => {
+                                        self.cancel_job(targetguild, &id);
+                                        format!("Cancelled job `{}`.", id)
+                                    }
+                                    None => format!("No pending job with ID `{}`.", id),
+                                };
+                                (command, content)
+                            }
+                            Command::ScheduleRemind { command, targetguild, invoker, channel, length, message } => {
+                                let job = Job {
+                                    job_id: format!("remind-{}", command.id),
+                                    due: Timestamp::now().unix_timestamp() + length.seconds_from_now().unwrap_or_default(),
+                                    kind: JobKind::Reminder {
+                                        channel,
+                                        invoker: invoker.id.get() as i64,
+                                        message,
+                                    },
+                                };
+                                let content = format!("Scheduled reminder: {}", job.describe());
+                                self.schedule_job(targetguild, job);
+                                (command, content)
+                            }
+                            _ => continue,
+                        };
+                        command
+                            .create_response(
+                                &ctx.http,
+                                CreateInteractionResponse::Message(
+                                    CreateInteractionResponseMessage::new()
+                                        .content(content)
+                                        .ephemeral(true),
+                                ),
+                            )
+                            .await
+                            .expect("Failed to send response");
+                    }
+                }
+            }
+        }
+    }
 
-                                }
+    async fn handle_punishment(&mut self, cmd: Command, ctx: Context) {
+        match cmd {
+            Command::PunishAdd {command, 
+                                target, 
+                                targetguild,
+                                invoker,
+                                ptype,
+                                reason,
+                                length,
+                                notify,
+                                evidence,
+                                delete_days,
+                                recorded, .. } => {  
+                let length = if ptype.is_instant() { Some(Length::Timed(0)) } else { length };
+                let now = Timestamp::now();
+                let end = length.unwrap_or(Length::Permanent).end_from(now);
+                let idkey = target.0.id.get() as i64;
+                match end {
+                    Ok(end) => {
+                        let details = RecordDetails {
+                            reason,
+                            evidence,
+                            punished_for: (now, end),
+                            delete_days,
+                            ..Default::default()
+                        };
+                        let Some(punishment) = self.process_punishment(&invoker, &target, ptype.clone(), details, &targetguild, &ctx).await else {
+                            // Dropping recorded tells ClientHandler not to purge.
+                            respond_add(&command, &ctx, &format!("The {:?} punishment for <@{}> could not be recorded.", ptype, idkey)).await;
+                            return;
+                        };
+                        let dm = notify.unwrap_or(self.get_config(&targetguild).dm_default);
+                        let delivered = self.enforce(targetguild, &target, &punishment, Delivery { moderator: Some(invoker.id), dm }, &ctx).await;
+                        let content = format!("Added {:?} punishment to <@{}>.{}", ptype, idkey, dm_status(delivered));
+
+                        respond_add(&command, &ctx, &content).await;
+
+                        // Escalating takes several more requests, so it runs after the response and amends it.
+                        let escalated = self.escalate(targetguild, &target, &punishment, &ctx).await;
+                        if !escalated.is_empty() {
+                            let steps = escalated
+                                .iter()
+                                .map(|step| format!(" Escalated to {:?} (ID {}).", step.punishment, step.id))
+                                .collect::<String>();
+                            if let Err(e) = command.edit_response(&ctx.http, EditInteractionResponse::new().content(format!("{}{}", content, steps))).await {
+                                eprintln!("Error amending response in Punishment Add: {}", e);
                             }
-                            _ => {}
                         }
+                        println!("Added punishment {:?} to user {}.", ptype, idkey);
+                        // Sent last, so the purge amends a response that is otherwise final.
+                        if let Some(recorded) = recorded
+                            && recorded.send(punishment.id).is_err()
+                        {
+                            eprintln!("Purge requester for user {} went away", idkey);
+                        }
+                    },
+                    Err(e) => {
+                        respond_add(&command, &ctx, &format!("Invalid timestamp conversion: {}", e)).await;
                     }
                 }
+                                
+            }
+            Command::PunishPurged { targetguild, target, invoker, id, purged } => {
+                let Some(mut userprofile) = self.get_profile(target.0.id.get() as i64, &targetguild).await else {
+                    return;
+                };
+                let Some(record) = userprofile.punishments.get_mut(&id) else {
+                    return;
+                };
+                record.purged = Some(purged);
+                self.update_profile(&mut userprofile, &targetguild, &target, &invoker, &ctx).await;
+            }
+            Command::PunishEdit {command, 
+                                target, 
+                                targetguild,
+                                invoker,
+                                id,
+                                latest,
+                                length,
+                                reason,
+                                notify,
+                                evidence, .. } => {  
+                let idkey = target.0.id.get() as i64;
+                if let Some(mut userprofile) = self
+                    .get_profile(idkey, &targetguild)
+                    .await
+                {
+                    let mut edited = userprofile.edit_punishment(id, latest, length, reason, evidence);
+                    if let Some(edited) = &mut edited
+                        && self.store_evidence(&targetguild, userprofile.user_thread, edited, &ctx).await
+                    {
+                        userprofile.punishments.insert(edited.id.clone(), edited.clone());
+                    }
+                    if let Some(edited) = &edited {
+                        // Each timed record owns its own temporary, so only the edited one is rescheduled.
+                        if length.is_some() && !matches!(edited.punishment, PunishmentType::Warn) && !edited.punishment.is_instant() {
+                            self.remove_temporary(idkey, &targetguild, &edited.id).await;
+                            if !edited.is_permanent() {
+                                self.add_temporary(targetguild, Temporary {
+                                    user_id: idkey,
+                                    negdur: !edited.punished_for.1.unix_timestamp(),
+                                    punishment: edited.clone(),
+                                }).await;
+                            }
+                            // Discord keeps its own timeout end, and an extended record may already have been lifted.
+                            if edited.is_active()
+                                && let Err(e) = self.apply(&ctx, targetguild, edited, &target.0).await
+                            {
+                                eprintln!("Error reapplying edited punishment in Punishment Edit: {}", e);
+                            }
+                        }
+                    }
+
+                    self.update_profile(&mut userprofile, &targetguild, &target, &invoker, &ctx).await;
+                    self.refresh_punished(targetguild, &userprofile).await;
+                    let mut delivered = true;
+                    if let Some(edited) = &edited {
+                        let dm = notify.unwrap_or(self.get_config(&targetguild).dm_default);
+                        delivered = self.notify_user(idkey, &targetguild, Notice::Edited, edited, Delivery { moderator: Some(invoker.id), dm }, &ctx).await;
+                    }
+
+                    command
+                            .create_response(
+                                &ctx.http,
+                                CreateInteractionResponse::Message(
+                                    CreateInteractionResponseMessage::new()
+                                        .content(format!("Edited punishment for <@{}>.{}", idkey, dm_status(delivered)))
+                                        .ephemeral(true),
+                                ),
+                            )
+                            .await
+                            .expect("Failed to send response");
+                                    
+                } else {
+                        command
+                            .create_response(
+                                &ctx.http,
+                                CreateInteractionResponse::Message(
+                                    CreateInteractionResponseMessage::new()
+                                        .content(format!("<@{}> lacks any punishment history.", idkey))
+                                        .ephemeral(true),
+                                ),
+                            )
+                            .await
+                            .expect("Failed to send response");
+                }
+            }
+            Command::PunishRemove {command, 
+                                    target, 
+                                    targetguild,
+                                    invoker,
+                                    id,
+                                    latest,
+                                    silent,
+                                    notify, .. } => {  
+                let idkey = target.0.id.get() as i64;
+                if let Some(mut userprofile) = self
+                    .get_profile(idkey, &targetguild)
+                    .await
+                {
+                    let removed = userprofile.remove_punishment(id, latest);
+                    let mut lift_error = None;
+                    if let Some(removed) = &removed {
+                        self.remove_temporary(idkey, &targetguild, &removed.id).await;
+                        // Ended records were already lifted, and lifting them again fails (i.e. Unknown Ban).
+                        if removed.is_active()
+                            && let Err(e) = self.lift(&ctx, targetguild, removed, &target.0).await
+                        {
+                            eprintln!("Error lifting punishment {} in Punishment Remove: {}", removed.id, e);
+                            lift_error = Some(e);
+                        }
+                    }
+
+                    self.update_profile(&mut userprofile, &targetguild, &target, &invoker, &ctx).await;
+                    self.refresh_punished(targetguild, &userprofile).await;
+                    let mut delivered = true;
+                    if let Some(removed) = &removed {
+                        let dm = notify.unwrap_or(self.get_config(&targetguild).dm_default);
+                        delivered = self.notify_user(idkey, &targetguild, Notice::Removed, removed, Delivery { moderator: Some(invoker.id), dm }, &ctx).await;
+                    }
+                    if !silent {
+                        command.
+                            create_response(
+                                &ctx.http,
+                                CreateInteractionResponse::Message(
+                                    CreateInteractionResponseMessage::new()
+                                        .content(format!("Removed punishment for <@{}>.{}{}",
+                                            target.0.id,
+                                            lift_error.map_or(String::new(), |e| format!(" It could not be lifted in Discord: {}.", e)),
+                                            dm_status(delivered)))
+                                        .ephemeral(true),
+                                ),
+                            )
+                            .await
+                            .expect("Failed to send response");                                   
+                    }
+                } else {
+                        command
+                            .create_response(
+                                &ctx.http,
+                                CreateInteractionResponse::Message(
+                                    CreateInteractionResponseMessage::new()
+                                        .content(format!("<@{}> lacks any punishment history.", target.0.id))
+                                        .ephemeral(true),
+                                ),
+                            )
+                            .await
+                            .expect("Failed to send response");
+                }
+            }
+            _ => { 
+            }
+        }
+    }
+
+    // Opened on the first request that needs the guild, later calls are no-ops.
+    async fn open_guild(&mut self, guild: GuildId) {
+        if self.database.contains_key(&guild) {
+            return;
+        }
+
+        if let Err(e) = std::fs::create_dir_all("server/databases") {
+            eprintln!("Failed to create database folder: {}", e);
+            return;
+        }
+
+        let db_path = format!("server/databases/{}.db", guild);
+        // Hang can occur here if improper drop, application closing needs to be handled eventually.
+        match Database::open_path(&db_path) {
+            Ok(db) => {
+                let profilecol = db.collection::<Profile>("Profile");
+                let tempcol = db.collection::<Temporary>("Temporary");
+                let rolecol = db.collection::<RolePermission>("RolePermission");
+                let jobcol = db.collection::<Job>("Jobs");
+                let configcol = db.collection::<GuildConfig>("GuildConfig");
+                let escalationcol = db.collection::<EscalationRule>("Escalation");
+
+                // Store with Bitwise ! duration to get the most recent punishment at the top
+                // ASC is the only working order (1)
+                if let Err(e) = profilecol.create_index(IndexModel {
+                    keys: doc! {
+                        "negdur": 1,
+                    },
+                    options: None,
+                }) {
+                    eprintln!(
+                        "Failed to create index for Profile collection in guild {}: {}",
+                        guild, e
+                    );
+                }
+
+                if let Err(e) = tempcol.create_index(IndexModel {
+                    keys: doc! {
+                        "negdur": 1,
+                    },
+                    options: None,
+                }) {
+                    eprintln!(
+                        "Failed to create index for Temporary collection in guild {}: {}",
+                        guild, e
+                    );
+                }
+
+                if let Err(e) = jobcol.create_index(IndexModel {
+                    keys: doc! {
+                        "due": 1,
+                    },
+                    options: None,
+                }) {
+                    eprintln!(
+                        "Failed to create index for Jobs collection in guild {}: {}",
+                        guild, e
+                    );
+                }
+                
+                self.database.insert(
+                    guild,
+                    GuildDB {
+                        db,
+                        profilecol,
+                        tempcol,
+                        rolecol,
+                        jobcol,
+                        configcol,
+                        escalationcol,
+                    },
+                );
+
+                self.restore_jobs(guild);
+                self.restore_punished(guild).await;
+                archive::Archive::for_guild(guild, &self.get_config(&guild)).prune();
+                let roleperms = self.list_roleperms(&guild).await;
+                self.roles.write().await.insert(guild, roleperms);
+            }
+            Err(e) => {
+                eprintln!(
+                    "Failed to initialize database for guild {}: {}",
+                    guild, e
+                );
             }
         }
     }
@@ -349,36 +710,47 @@ impl DBHandler {
         return None;
     }
 
-    async fn process_punishment(&self, userid: i64, invoker: &User, target: &(User, Option<PartialMember>), ptype: PunishmentType, reason: Option<String>, punishtime: (Timestamp, Timestamp), guildid: &GuildId, ctx: &Context) -> Option<PunishmentRecord> {
+    // The points of details are set from the guild config here.
+    async fn process_punishment(&self, invoker: &User, target: &(User, Option<PartialMember>), ptype: PunishmentType, details: RecordDetails, guildid: &GuildId, ctx: &Context) -> Option<PunishmentRecord> {
+        let userid = target.0.id.get() as i64;
+        let config = self.get_config(guildid);
+        let details = RecordDetails { points: config.points.weight(&ptype), ..details };
         if let Some(guilddb) = self.database.get(guildid) {
             match guilddb.profilecol.find_one(doc! { "user_id": userid}) {
                 Ok(Some(mut profile)) =>  {
-                    let (profile, punishment) = profile.add_punishment(ptype,reason,punishtime, invoker.id.get() as i64);
-                    self.update_profile(&profile, guildid, target, invoker, ctx).await;
+                    let (profile, mut punishment) = profile.add_punishment(ptype, invoker.id.get() as i64, details);
+                    if self.store_evidence(guildid, profile.user_thread, &mut punishment, ctx).await {
+                        profile.punishments.insert(punishment.id.clone(), punishment.clone());
+                    }
+                    self.update_profile(profile, guildid, target, invoker, ctx).await;
                     return Some(punishment);
                 },
                 Ok(None) => {
                     if let Some((log,_)) = self.threadlog.get(guildid) {
                         let id = "1".to_string();
-                        let punishment = PunishmentRecord {
-                            id: id.clone(),
-                            punishment: ptype,
-                            reason,
-                            punished_for: punishtime,
-                            moderator: invoker.id.get() as i64,
-                        };
+                        let mut punishment = PunishmentRecord::new(id.clone(), ptype, invoker.id.get() as i64, details);
 
                         let mut newpunishment = BTreeMap::new();
                         newpunishment.insert(id, punishment.clone());
-                        let embed = profembed(invoker, target, &newpunishment).await;
-                        let userthread = match create_user_profile(log, ctx, embed, userid).await {
+                        let embed = profembed(invoker, target, &newpunishment, &[], false, &config).await;
+                        let userthread = match create_user_profile(log, ctx, embed, userid, vec![profile_buttons(*guildid, userid)]).await {
                             Ok(channelid) => channelid,
                             Err(e) => {
                                 eprintln!("Error creating user profile thread in Profile Query: {}", e);
                                 return None;
                             }
                         };
-                        if let Err(e) = guilddb.profilecol.insert_one(Profile::new(userid, userthread, newpunishment)) {
+                        let mut profile = Profile::new(userid, userthread, newpunishment);
+                        profile.refresh_points(&config);
+                        // The thread only exists now, so evidence is stored before the history page is posted.
+                        if self.store_evidence(guildid, userthread, &mut punishment, ctx).await {
+                            profile.punishments.insert(punishment.id.clone(), punishment.clone());
+                        }
+                        match sync_history_posts(ctx, &userthread, history_pages(&profile.punishments, 0), &[]).await {
+                            Ok(posts) => profile.history_posts = posts,
+                            Err(e) => eprintln!("Error posting profile history in Profile Query: {}", e),
+                        }
+                        if let Err(e) = guilddb.profilecol.insert_one(profile) {
                             eprintln!("Error creating new profile in Database: {}", e);
                             return None;
                         }
@@ -399,13 +771,26 @@ impl DBHandler {
 
     }
     
-    async fn update_profile(&self, profile: &Profile, guildid: &GuildId, target: &(User, Option<PartialMember>), invoker: &User, ctx: &Context) {
-        update_thread_post(ctx, 
+    async fn update_profile(&self, profile: &mut Profile, guildid: &GuildId, target: &(User, Option<PartialMember>), invoker: &User, ctx: &Context) {
+        let config = self.get_config(guildid);
+        profile.refresh_points(&config);
+        if let Err(e) = update_thread_post(ctx, 
             &profile.user_thread,
-                profembed(invoker, target, &profile.punishments).await
+                profembed(invoker, target, &profile.punishments, &profile.automod, profile.quarantined, &config).await,
+                vec![profile_buttons(*guildid, profile.user_id)]
             )
-        .await
-        .expect("Failed to update profile thread embed");
+            .await
+        {
+            eprintln!("Error updating profile thread embed in Profile Update: {}", e);
+        }
+        match sync_history_posts(ctx, &profile.user_thread, history_pages(&profile.punishments, 0), &profile.history_posts).await {
+            Ok(posts) => profile.history_posts = posts,
+            Err(e) => eprintln!("Error updating profile history in Profile Update: {}", e),
+        }
+        self.save_profile(profile, guildid);
+    }
+
+    fn save_profile(&self, profile: &Profile, guildid: &GuildId) {
         if let Some(guilddb) = self.database.get(guildid) {
             if let Ok(bson_profile) = polodb_core::bson::to_bson(profile) {
                 guilddb.profilecol.update_one(doc! { "user_id": profile.user_id }, doc! { "$set": bson_profile })
@@ -418,59 +803,222 @@ impl DBHandler {
         }
     }
 
-    async fn add_temporary(&mut self, command: CommandInteraction, userid: i64, target: (User, Option<PartialMember>), targetguild: GuildId, invoker: User, record: Temporary) {
-        //Temporary task thread generation
-        let handle_sender = self.sender.clone();
-        let handle_ctx = self.context.clone();
-        let handle_command = Command::PunishRemove { 
-            command,
-            targetguild,
-            target,
-            invoker,
-            latest: None,
-            id: Some(record.punishment.id.clone()),
-            silent: true,
-        };
+    async fn add_temporary(&mut self, targetguild: GuildId, record: Temporary) {
+        if let Some(guilddb) = self.database.get(&targetguild) {
+            if let Err(e) = guilddb.tempcol.insert_one(&record) {
+                eprintln!("Error creating new temporary in Temporary Add: {}", e);
+                return;
+            }
+        } else {
+            eprintln!("No database found for queried guild in Profile Update");
+            return;
+        }
+        self.schedule_job(targetguild, Job::expire(&record));
+    }
+
+    async fn expire_temporary(&mut self, userid: i64, guildid: &GuildId, pid: String, ctx: &Context) {
+        self.remove_temporary(userid, guildid, &pid).await;
 
-        let handle = tokio::spawn(async move {
-            let sleeptime = Duration::from_secs((record.punishment.punished_for.1.unix_timestamp() - record.punishment.punished_for.0.unix_timestamp()) as u64);             
-            sleep(sleeptime).await;
-            
-            if let Err(e) = handle_sender.send(DBRequest {
-                request_type: DBRequestType::Punishment,
-                command: Some(handle_command),
-                context: handle_ctx,
-                threadlog: None,
-            }).await {
-                eprintln!("Failed to send TemporaryComplete request: {}", e);
+        if let Some(mut userprofile) = self.get_profile(userid, guildid).await {
+            if let Some(record) = userprofile.punishments.get(&pid).cloned() {
+                let user = match UserId::new(userid as u64).to_user(ctx).await {
+                    Ok(user) => user,
+                    Err(e) => {
+                        eprintln!("Error fetching user in Temporary Expire: {}", e);
+                        return;
+                    }
+                };
+                if let Err(e) = self.lift(ctx, *guildid, &record, &user).await {
+                    eprintln!("Error lifting expired punishment in Temporary Expire: {}", e);
+                }
+                let member = guildid.member(&ctx.http, user.id).await.ok().map(PartialMember::from);
+                let bot = User::from((*ctx.cache.current_user()).clone());
+                self.update_profile(&mut userprofile, guildid, &(user, member), &bot, ctx).await;
+                self.notify_user(userid, guildid, Notice::Expired, &record, Delivery { moderator: None, dm: self.get_config(guildid).dm_default }, ctx).await;
             }
-            println!("Temporary punishment for user {} has completed.", userid);
-        });
+            self.refresh_punished(*guildid, &userprofile).await;
+        }
+    }
 
-        self.active_temps.insert(userid, (targetguild, record.clone(), handle));
+    // Bans and timeouts are marked as the bot's own before they're sent, their events can arrive before the call returns.
+    async fn apply(&self, ctx: &Context, guildid: GuildId, record: &PunishmentRecord, target: &User) -> Result<(), SerenityError> {
+        self.mark_own(guildid, target, &record.punishment).await;
+        apply_punishment(ctx, guildid, record, target, &self.get_config(&guildid)).await
+    }
 
-        if let Some(guilddb) = self.database.get(&targetguild) {
-            if let Err(e) = guilddb.tempcol.insert_one(record) {
-                eprintln!("Error creating new temporary in Temporary Add: {}", e);
+    async fn lift(&self, ctx: &Context, guildid: GuildId, record: &PunishmentRecord, target: &User) -> Result<(), SerenityError> {
+        self.mark_own(guildid, target, &record.punishment).await;
+        remove_punishment(ctx, guildid, record, target, &self.get_config(&guildid)).await
+    }
+
+    async fn mark_own(&self, guildid: GuildId, target: &User, punishment: &PunishmentType) {
+        // A softban raises a ban and an unban like any other ban.
+        let kind = match punishment {
+            PunishmentType::Ban | PunishmentType::Softban => PunishmentType::Ban,
+            PunishmentType::Timeout => PunishmentType::Timeout,
+            _ => return,
+        };
+        let now = Timestamp::now();
+        let mut own = self.own_actions.write().await;
+        own.retain(|_, at| now.unix_timestamp() - at.unix_timestamp() <= OWN_ACTION_TTL);
+        own.insert((guildid, target.id.get() as i64, kind), now);
+    }
+
+    async fn refresh_punished(&self, guildid: GuildId, profile: &Profile) {
+        let mut punished = self.punished.write().await;
+        if profile.rejoin_punishments().next().is_some() {
+            let timed_out = profile.rejoin_punishments().any(|record| record.punishment == PunishmentType::Timeout);
+            punished.insert((guildid, profile.user_id), timed_out);
+        } else {
+            punished.remove(&(guildid, profile.user_id));
+        }
+    }
+
+    async fn restore_punished(&self, guildid: GuildId) {
+        let profiles = if let Some(guilddb) = self.database.get(&guildid) {
+            match guilddb.profilecol.find(doc! {}).run() {
+                Ok(cursor) => cursor.filter_map(|profile| profile.ok()).collect::<Vec<Profile>>(),
+                Err(e) => {
+                    eprintln!("Error retrieving profiles in Punished Restore: {}", e);
+                    return;
+                }
             }
         } else {
-            eprintln!("No database found for queried guild in Profile Update");
+            eprintln!("No database found for queried guild in Punished Restore");
+            return;
+        };
+        for profile in profiles {
+            self.refresh_punished(guildid, &profile).await;
+        }
+    }
+
+    async fn reapply_punishments(&self, guildid: GuildId, target: (User, Option<PartialMember>), ctx: &Context) {
+        let idkey = target.0.id.get() as i64;
+        let Some(mut userprofile) = self.get_profile(idkey, &guildid).await else {
+            self.punished.write().await.remove(&(guildid, idkey));
+            return;
+        };
+
+        let mut reapplied = vec![];
+        for record in userprofile.rejoin_punishments() {
+            match self.apply(ctx, guildid, record, &target.0).await {
+                Ok(_) => reapplied.push(format!("{:?} (ID {})", record.punishment, record.id)),
+                Err(e) => eprintln!("Error reapplying punishment {} in Rejoin: {}", record.id, e),
+            }
+        }
+
+        if !reapplied.is_empty() {
+            println!("Reapplied punishments to rejoining user {} in guild {}.", idkey, guildid);
+            if let Err(e) = note_thread(ctx, &userprofile.user_thread,
+                format!("⚠️ <@{}> rejoined while punished, reapplied: {}", idkey, reapplied.join(", "))).await {
+                eprintln!("Error noting rejoin in Rejoin: {}", e);
+            }
+            let bot = User::from((*ctx.cache.current_user()).clone());
+            self.update_profile(&mut userprofile, &guildid, &target, &bot, ctx).await;
         }
+        self.refresh_punished(guildid, &userprofile).await;
     }
 
-    async fn remove_temporary(&mut self, userid: i64, guildid: &GuildId) -> Option<String> {
-        if let Some((_, temp, handle)) = self.active_temps.remove(&userid) {
-            handle.abort();
-            if let Some(guilddb) = self.database.get(guildid) {
-                if let Err(e) = guilddb.tempcol.delete_one(doc! { "user_id": userid }) {
-                    eprintln!("Error removing temporary in Temporary Remove: {}", e);
+    // Applies a newly added record and tells the user, returns false when a requested direct message couldn't be delivered.
+    // Bans, kicks and softbans are messaged first, since a user can't be reached once they no longer share a guild with the bot.
+    async fn enforce(&mut self, guildid: GuildId, target: &(User, Option<PartialMember>), record: &PunishmentRecord, delivery: Delivery, ctx: &Context) -> bool {
+        let idkey = target.0.id.get() as i64;
+        let removed = matches!(record.punishment, PunishmentType::Ban | PunishmentType::Kick | PunishmentType::Softban);
+        let mut delivered = true;
+        if removed {
+            delivered = self.notify_user(idkey, &guildid, Notice::Added, record, delivery, ctx).await;
+        }
+
+        if let Err(e) = self.apply(ctx, guildid, record, &target.0).await {
+            eprintln!("Error applying punishment {} in Punishment Enforce: {}", record.id, e);
+        }
+
+        if matches!(record.punishment, PunishmentType::Mute | PunishmentType::Timeout) {
+            *self.punished.write().await.entry((guildid, idkey)).or_default() |= record.punishment == PunishmentType::Timeout;
+        }
+
+        if !removed {
+            delivered = self.notify_user(idkey, &guildid, Notice::Added, record, delivery, ctx).await;
+        }
+
+        if record.is_active() && !record.is_permanent() {
+            self.add_temporary(guildid, Temporary {
+                user_id: idkey,
+                punishment: record.clone(),
+                negdur: !Timestamp::now().unix_timestamp(),
+            }).await;
+        }
+        delivered
+    }
+
+    // Notices always go to the user's private notifier thread, which is created and stored on the profile the first time.
+    // With dm set they are also sent by direct message, returns false when that couldn't be delivered.
+    async fn notify_user(&self, userid: i64, guildid: &GuildId, notice: Notice, record: &PunishmentRecord, delivery: Delivery, ctx: &Context) -> bool {
+        let message = notice_message(notice, record, self.get_config(guildid).appeal.as_deref());
+        // Only new or changed punishments can be appealed.
+        let components = || match notice {
+            Notice::Added | Notice::Edited => vec![appeal_button(*guildid, userid, &record.id)],
+            _ => vec![],
+        };
+        let delivered = !delivery.dm || match dm_notice(ctx, *guildid, UserId::new(userid as u64), &message, components()).await {
+            Ok(_) => true,
+            Err(e) => {
+                eprintln!("Error sending direct message in Notify: {}", e);
+                false
+            }
+        };
+
+        // Banned, kicked and softbanned users are no longer members and can't be added to a thread.
+        if matches!(record.punishment, PunishmentType::Ban | PunishmentType::Kick | PunishmentType::Softban) {
+            return delivered;
+        }
+        let (Some((_, notifier)), Some(guilddb)) = (self.threadlog.get(guildid), self.database.get(guildid)) else {
+            eprintln!("No notifier found for queried guild in Notify");
+            return delivered;
+        };
+        let Some(userprofile) = self.get_profile(userid, guildid).await else {
+            return delivered;
+        };
+
+        let thread = match userprofile.notify_thread {
+            Some(thread) => thread,
+            None => {
+                let members = std::iter::once(UserId::new(userid as u64)).chain(delivery.moderator).collect::<Vec<UserId>>();
+                match create_notify_thread(notifier, ctx, userid, &members).await {
+                    Ok(thread) => {
+                        if let Ok(bson_thread) = polodb_core::bson::to_bson(&thread)
+                            && let Err(e) = guilddb.profilecol.update_one(doc! { "user_id": userid }, doc! { "$set": { "notify_thread": bson_thread } })
+                        {
+                            eprintln!("Error saving notify thread in Notify: {}", e);
+                        }
+                        thread
+                    }
+                    Err(e) => {
+                        eprintln!("Error creating notify thread in Notify: {}", e);
+                        return delivered;
+                    }
                 }
-            } else {
-                eprintln!("No database found for queried guild in Profile Update");
             }
-            Some(temp.punishment.id)
+        };
+
+        let mut note = format!("<@{}> {}", userid, message);
+        if !delivered {
+            note.push_str("\n*Your direct messages are closed, so this notice was only posted here.*");
+        }
+        if let Err(e) = thread.send_message(&ctx.http, CreateMessage::new().content(note).components(components())).await {
+            eprintln!("Error posting notice in Notify: {}", e);
+        }
+        delivered
+    }
+
+    async fn remove_temporary(&mut self, userid: i64, guildid: &GuildId, pid: &str) {
+        self.cancel_job(*guildid, &Job::expire_id(userid, pid));
+        if let Some(guilddb) = self.database.get(guildid) {
+            if let Err(e) = guilddb.tempcol.delete_one(doc! { "user_id": userid, "punishment.id": pid }) {
+                eprintln!("Error removing temporary in Temporary Remove: {}", e);
+            }
         } else {
-            None
+            eprintln!("No database found for queried guild in Profile Update");
         }
     }
 
@@ -495,6 +1043,43 @@ impl DBHandler {
         }
         return None;
     }
+
+    async fn set_roleperm(&self, roleid: i64, capability: Option<Capability>, allow: bool, guildid: &GuildId) -> bool {
+        let Some(mut roleperm) = self.get_roleperm(roleid, guildid).await else {
+            return false;
+        };
+        roleperm.set(capability, allow);
+        if let Some(guilddb) = self.database.get(guildid) {
+            if let Ok(bson_role) = polodb_core::bson::to_bson(&roleperm) {
+                if let Err(e) = guilddb.rolecol.update_one(doc! { "role_id": roleid }, doc! { "$set": bson_role }) {
+                    eprintln!("Error updating role in Role Update: {}", e);
+                    return false;
+                }
+                true
+            } else {
+                eprintln!("Error converting role to BSON in Role Update");
+                false
+            }
+        } else {
+            eprintln!("No database found for queried guild in Role Update");
+            false
+        }
+    }
+
+    async fn list_roleperms(&self, guildid: &GuildId) -> Vec<RolePermission> {
+        if let Some(guilddb) = self.database.get(guildid) {
+            match guilddb.rolecol.find(doc! {}).run() {
+                Ok(cursor) => cursor.filter_map(|role| role.ok()).collect(),
+                Err(e) => {
+                    eprintln!("Error retrieving roles in Role List: {}", e);
+                    Vec::new()
+                }
+            }
+        } else {
+            eprintln!("No database found for queried guild in Role List");
+            Vec::new()
+        }
+    }
 }
 
 pub enum DBRequestType {
@@ -503,6 +1088,36 @@ pub enum DBRequestType {
     FetchProfile,
     Punishment,
     CommandPermissionUpdate,
+    Schedule,
+    Rejoin,
+    Config,
+    Appeal,
+    Evidence,
+    Escalation,
+    External,
+    AutoMod,
+}
+
+// Appended to moderator responses when a requested direct message didn't go through.
+fn dm_status(delivered: bool) -> &'static str {
+    if delivered { "" } else { " Their direct messages are closed." }
+}
+
+// How a notice reaches the user, the moderator is added to the notifier thread if it has to be created.
+#[derive(Debug, Clone, Copy)]
+struct Delivery {
+    moderator: Option<UserId>,
+    dm: bool,
+}
+
+async fn respond_add(command: &Responder, ctx: &Context, content: &str) {
+    if let Err(e) = command.create_response(&ctx.http, CreateInteractionResponse::Message(
+        CreateInteractionResponseMessage::new()
+            .content(content)
+            .ephemeral(true),
+    )).await {
+        eprintln!("Error responding in Punishment Add: {}", e);
+    }
 }
 
 struct GuildDB {
@@ -510,6 +1125,9 @@ struct GuildDB {
     profilecol: polodb_core::Collection<Profile>,
     tempcol: polodb_core::Collection<Temporary>,
     rolecol: polodb_core::Collection<RolePermission>,
+    jobcol: polodb_core::Collection<Job>,
+    configcol: polodb_core::Collection<GuildConfig>,
+    escalationcol: polodb_core::Collection<EscalationRule>,
 }
 
 pub struct DBRequest {
@@ -520,34 +1138,63 @@ pub struct DBRequest {
 }
 
 pub enum Command {
+    AppealSubmit {
+        command: Responder,
+        targetguild: GuildId,
+        user: User,
+        pid: String,
+        statement: String,
+    },
+    AppealDecide {
+        command: Responder,
+        id: AppealId,
+        target: (User, Option<PartialMember>),
+        decision: AppealState,
+        length: Option<Length>,
+    },
     PunishEdit {
-        command: CommandInteraction,
+        command: Responder,
         targetguild: GuildId,
         target: (User, Option<PartialMember>),
         invoker: User,
         reason: Option<String>,
-        length: Option<i64>,
+        length: Option<Length>,
         latest: Option<bool>,
         id: Option<String>,
+        notify: Option<bool>,
+        evidence: Vec<Evidence>,
     },
     PunishRemove {
-        command: CommandInteraction,
+        command: Responder,
         targetguild: GuildId,
         target: (User, Option<PartialMember>),
         invoker: User,
         latest: Option<bool>,
         id: Option<String>,
         silent: bool,
+        notify: Option<bool>,
     },
 
     PunishAdd {
-        command: CommandInteraction,
+        command: Responder,
         targetguild: GuildId,
         target: (User, Option<PartialMember>),
         invoker: User,
         ptype: PunishmentType,
         reason: Option<String>,
-        length: Option<i64>,
+        length: Option<Length>,
+        notify: Option<bool>,
+        evidence: Vec<Evidence>,
+        delete_days: u8,
+        recorded: Option<oneshot::Sender<String>>, //Gets the record id once added, a purge waits on it
+    },
+
+    PunishPurged {
+        targetguild: GuildId,
+        target: (User, Option<PartialMember>),
+        invoker: User,
+        id: String,
+        purged: u32,
     },
 
     RoleAdjust {
@@ -556,21 +1203,117 @@ pub enum Command {
         target: Role,
         invoker: User,
         allow: bool,
+        capability: Option<Capability>,
     },
     
     FetchProfile {
+        command: Responder,
+        targetguild: GuildId,
+        target: (User, Option<PartialMember>),
+        invoker: User,
+        page: usize,
+    },
+
+    EvidenceGet {
         command: CommandInteraction,
         targetguild: GuildId,
         target: (User, Option<PartialMember>),
+        id: String,
+    },
+
+    GetConfig {
+        targetguild: GuildId,
+        reply: oneshot::Sender<GuildConfig>,
+    },
+
+    SaveConfig {
+        targetguild: GuildId,
+        config: GuildConfig,
+    },
+
+    ModConfigView {
+        command: CommandInteraction,
+        targetguild: GuildId,
+    },
+
+    ModConfigSet {
+        command: CommandInteraction,
+        targetguild: GuildId,
         invoker: User,
+        changes: ConfigChanges,
+    },
+
+    Rejoin {
+        targetguild: GuildId,
+        target: (User, Option<PartialMember>),
+    },
+
+    AutoModHit {
+        targetguild: GuildId,
+        target: (User, Option<PartialMember>),
+        hit: AutoModHit,
+    },
+
+    Quarantine {
+        targetguild: GuildId,
+        target: (User, Option<PartialMember>),
+        quarantined: bool,
+    },
+
+    External {
+        targetguild: GuildId,
+        target: (User, Option<PartialMember>),
+        change: ExternalChange,
+        moderator: Option<User>, //From the audit log, None when no entry was found
+        reason: Option<String>,
+    },
+
+    ScheduleList {
+        command: CommandInteraction,
+        targetguild: GuildId,
+    },
+
+    EscalationList {
+        command: CommandInteraction,
+        targetguild: GuildId,
+    },
+
+    EscalationAdd {
+        command: CommandInteraction,
+        targetguild: GuildId,
+        invoker: User,
+        rule: EscalationRule,
+    },
+
+    EscalationRemove {
+        command: CommandInteraction,
+        targetguild: GuildId,
+        id: String,
+    },
+
+    ScheduleCancel {
+        command: CommandInteraction,
+        targetguild: GuildId,
+        id: String,
+    },
+
+    ScheduleRemind {
+        command: CommandInteraction,
+        targetguild: GuildId,
+        invoker: User,
+        channel: ChannelId,
+        length: Length,
+        message: String,
     },
 }
 
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct RolePermission {
     pub role_id: i64,
-    pub allow: bool,
+    pub allow: bool, //Set while any capability is granted
+    #[serde(default)]
+    pub capabilities: Option<BTreeSet<Capability>>, //None for roles saved before capabilities, where allow granted everything
 }
 
 impl RolePermission {
@@ -578,8 +1321,35 @@ impl RolePermission {
         RolePermission {
             role_id,
             allow: false,
+            capabilities: Some(BTreeSet::new()),
+        }
+    }
+
+    pub fn has(&self, capability: Capability) -> bool {
+        match &self.capabilities {
+            Some(capabilities) => capabilities.contains(&capability),
+            None => self.allow,
         }
     }
+
+    pub fn set(&mut self, capability: Option<Capability>, allow: bool) {
+        let mut capabilities = Capability::ALL
+            .into_iter()
+            .filter(|c| self.has(*c))
+            .collect::<BTreeSet<Capability>>();
+        match capability {
+            Some(capability) if allow => {
+                capabilities.insert(capability);
+            }
+            Some(capability) => {
+                capabilities.remove(&capability);
+            }
+            None if allow => capabilities.extend(Capability::ALL),
+            None => capabilities.clear(),
+        }
+        self.allow = !capabilities.is_empty();
+        self.capabilities = Some(capabilities);
+    }
 }
 
 /*
@@ -594,7 +1364,19 @@ Maybe consider keeping struct elements of the embed for easy recreation?
 pub struct Profile {
     user_id: i64,
     user_thread: ChannelId,
+    #[serde(default)]
+    notify_thread: Option<ChannelId>, //Private thread in the notifier channel, created on the first notice
     pub punishments: BTreeMap<String, PunishmentRecord>, //id, Record
+    #[serde(default)]
+    pub appeals: BTreeMap<String, Appeal>, //Punishment id, Appeal. Kept after an approved appeal removes its record
+    #[serde(default)]
+    history_posts: Vec<MessageId>, //History pages following the summary in user_thread, newest records first
+    #[serde(default)]
+    points: u32, //Point total when the profile was last refreshed
+    #[serde(default)]
+    pub automod: Vec<AutoModHit>, //Oldest first, these never count as punishments
+    #[serde(default)]
+    pub quarantined: bool, //Set while AutoMod blocks the member's interactions
     negdur: i64,
 }
 
@@ -605,6 +1387,68 @@ pub struct PunishmentRecord {
     pub reason: Option<String>,
     pub punished_for: (Timestamp, Timestamp), //Start, End
     pub moderator: i64,
+    #[serde(default)]
+    pub evidence: Vec<Evidence>,
+    #[serde(default)]
+    pub automatic: Option<AutoAction>, //Set when issued by an escalation rule
+    #[serde(default)]
+    pub expired: Option<Timestamp>, //Set by the decay sweep, expired records no longer count
+    #[serde(default)]
+    pub points: Option<u32>, //Weight when given, None for records from before points
+    #[serde(default)]
+    pub delete_days: u8, //Days of messages deleted by a ban or softban
+    #[serde(default)]
+    pub purged: Option<u32>, //Messages deleted by the purge option, None when none was asked for or it failed
+}
+
+// What a new record carries besides its type and moderator.
+#[derive(Debug, Default)]
+pub struct RecordDetails {
+    pub reason: Option<String>,
+    pub evidence: Vec<Evidence>,
+    pub punished_for: (Timestamp, Timestamp), //Start, End
+    pub points: u32,
+    pub delete_days: u8,
+}
+
+impl PunishmentRecord {
+    pub fn new(id: String, punishment: PunishmentType, moderator: i64, details: RecordDetails) -> Self {
+        PunishmentRecord {
+            id,
+            punishment,
+            reason: details.reason,
+            punished_for: details.punished_for,
+            moderator,
+            evidence: details.evidence,
+            automatic: None,
+            expired: None,
+            points: Some(details.points),
+            delete_days: details.delete_days,
+            purged: None,
+        }
+    }
+
+    // Permanent punishments end at the epoch.
+    pub fn is_permanent(&self) -> bool {
+        self.punished_for.1 == Timestamp::default()
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.is_permanent() || self.punished_for.1 > Timestamp::now()
+    }
+
+    // Warnings decay from when they were given, other types only once they have ended.
+    pub fn decay_due(&self, days: u32) -> Option<Timestamp> {
+        if days == 0 || self.expired.is_some() {
+            return None;
+        }
+        let from = match self.punishment {
+            PunishmentType::Warn => self.punished_for.0,
+            _ if self.is_permanent() => return None,
+            _ => self.punished_for.1,
+        };
+        Timestamp::from_unix_timestamp(from.unix_timestamp() + days as i64 * 86400).ok()
+    }
 }
 
 impl Profile {
@@ -612,24 +1456,41 @@ impl Profile {
         Profile {
             user_id,
             user_thread,
+            notify_thread: None,
             punishments,
+            appeals: BTreeMap::new(),
+            history_posts: Vec::new(),
+            points: 0,
+            automod: Vec::new(),
+            quarantined: false,
             negdur: !Timestamp::now().unix_timestamp(),
         }
     }
 
-    pub fn add_punishment(&mut self, punishment: PunishmentType, reason: Option<String>, punished_for:(Timestamp, Timestamp), moderator: i64) -> (&mut Profile, PunishmentRecord) {
+    // Ids are string keys, so "10" sorts before "9" and the latest has to be found numerically.
+    pub fn latest_id(&self) -> Option<String> {
+        self.punishments
+            .keys()
+            .max_by_key(|id| id.parse::<u16>().unwrap_or(0))
+            .cloned()
+    }
+
+    // Active punishments that are lost when a member leaves and have to be reapplied on rejoin.
+    pub fn rejoin_punishments(&self) -> impl Iterator<Item = &PunishmentRecord> {
+        self.punishments
+            .values()
+            .filter(|record| record.is_active() && matches!(record.punishment, PunishmentType::Mute | PunishmentType::Timeout))
+    }
+
+    pub fn add_punishment(&mut self, punishment: PunishmentType, moderator: i64, details: RecordDetails) -> (&mut Profile, PunishmentRecord) {
         self.negdur =!Timestamp::now().unix_timestamp();
-        let id = match self.punishments.keys().last() {
+        let id = match self.latest_id() {
             Some(last_id) => last_id.parse::<u16>().unwrap_or(0) + 1,
             None => 1,
         }.to_string();
-        let record = PunishmentRecord {
-            id: id.clone(),
-            punishment,
-            reason,
-            punished_for,
-            moderator,
-        };
+        let record = PunishmentRecord::new(id.clone(), punishment, moderator, details);
+        // Ids can be reused once the latest record is removed, so a stale appeal must not carry over.
+        self.appeals.remove(&id);
         self.punishments.insert(id, record.clone());
         (self, record)
     }
@@ -645,7 +1506,7 @@ impl Profile {
                 }
             }
             (None, Some(true)) => {
-                if let Some(last_id) = self.punishments.keys().last().cloned() {
+                if let Some(last_id) = self.latest_id() {
                     if let Some(record) = self.punishments.remove(&last_id) {
                         return Some(record);
                     } else {
@@ -659,45 +1520,36 @@ impl Profile {
         }
     }
 
-    pub fn edit_punishment(&mut self, id: Option<String>, latest: Option<bool>, length: Option<i64>, reason: Option<String>, temp_record: Option<&mut Temporary >) {
+    pub fn edit_punishment(&mut self, id: Option<String>, latest: Option<bool>, length: Option<Length>, reason: Option<String>, evidence: Vec<Evidence>) -> Option<PunishmentRecord> {
          self.negdur =!Timestamp::now().unix_timestamp();
-         match (id, latest) {
-            (Some(pid), _) => {
-                if let Some(record) = self.punishments.get_mut(&pid) {
-                    if let Some(reason) = reason {
-                        record.reason = Some(reason);
-                    }
-                    if let Some(length) = length {
-                        let start = record.punished_for.0.unix_timestamp();
-                        if let Ok(end) = Timestamp::from_unix_timestamp(start + length) {
-                            record.punished_for.1 = end;
-                        } else {
-                            eprintln!("Error converting timestamp in Edit Punishment");
-                        }
-                    }
-                }
-            }
+         let record = match (id, latest) {
+            (Some(pid), _) => self.punishments.get_mut(&pid)?,
             (None, Some(true)) => {
-                if let Some((_, record)) = self.punishments.iter_mut().next_back() {
-                    if let Some(reason) = reason {
-                        record.reason = Some(reason);
-                    }
-                    if let Some(length) = length {
-                        let start = record.punished_for.0.unix_timestamp();
-                        if let Ok(end) = Timestamp::from_unix_timestamp(start + length) {
-                            record.punished_for.1 = end;
-                            if let Some(temp_record) = temp_record {
-                                temp_record.punishment = record.clone();
-                                temp_record.negdur = !end.unix_timestamp();
-                            }
-                        } else {
-                            eprintln!("Error converting timestamp in Edit Punishment");
-                        }
-                    }
-                }
+                let last_id = self.latest_id()?;
+                self.punishments.get_mut(&last_id)?
+            }
+            _ => return None,
+        };
+        if let Some(reason) = reason {
+            record.reason = Some(reason);
+        }
+        record.evidence.extend(evidence);
+        if let Some(length) = length.filter(|_| !record.punishment.is_instant()) {
+            let end = match length {
+                // Extending counts from the current end, or from now once it has passed.
+                Length::Extend(_) if record.is_permanent() => Ok(record.punished_for.1),
+                Length::Extend(_) => length.end_from(record.punished_for.1.max(Timestamp::now())),
+                _ => length.end_from(record.punished_for.0),
+            };
+            if let Ok(end) = end {
+                // A new end restarts the decay window, the next sweep decides again.
+                record.punished_for.1 = end;
+                record.expired = None;
+            } else {
+                eprintln!("Error converting timestamp in Edit Punishment");
             }
-            _ => {}
         }
+        Some(record.clone())
     }
 }
 
@@ -706,4 +1558,82 @@ pub struct Temporary {
     user_id: i64,
     punishment: PunishmentRecord,
     negdur: i64,
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::fixtures::{DAY, at, record};
+
+    #[test]
+    fn warns_decay_from_their_start() {
+        let warn = record(PunishmentType::Warn, 1000 * DAY, None);
+        assert_eq!(warn.decay_due(30).map(|due| due.unix_timestamp()), Some(1030 * DAY));
+    }
+
+    #[test]
+    fn other_types_decay_from_their_end() {
+        let timeout = record(PunishmentType::Timeout, 1000 * DAY, Some(1002 * DAY));
+        assert_eq!(timeout.decay_due(7).map(|due| due.unix_timestamp()), Some(1009 * DAY));
+        let kick = record(PunishmentType::Kick, 1000 * DAY, Some(1000 * DAY));
+        assert_eq!(kick.decay_due(7).map(|due| due.unix_timestamp()), Some(1007 * DAY));
+    }
+
+    #[test]
+    fn permanent_records_never_decay() {
+        assert_eq!(record(PunishmentType::Ban, 1000 * DAY, None).decay_due(30), None);
+        assert_eq!(record(PunishmentType::Mute, 1000 * DAY, None).decay_due(30), None);
+    }
+
+    #[test]
+    fn no_window_or_expired_never_decays() {
+        assert_eq!(record(PunishmentType::Warn, 1000 * DAY, None).decay_due(0), None);
+        let mut warn = record(PunishmentType::Warn, 1000 * DAY, None);
+        warn.expired = Some(at(1030 * DAY));
+        assert_eq!(warn.decay_due(30), None);
+    }
+
+    #[test]
+    fn new_roles_have_no_capabilities() {
+        let roleperm = RolePermission::new(1);
+        assert!(!roleperm.allow);
+        assert!(Capability::ALL.iter().all(|capability| !roleperm.has(*capability)));
+    }
+
+    #[test]
+    fn sets_single_capabilities() {
+        let mut roleperm = RolePermission::new(1);
+        roleperm.set(Some(Capability::Warn), true);
+        roleperm.set(Some(Capability::Mute), true);
+        assert!(roleperm.has(Capability::Warn) && roleperm.has(Capability::Mute));
+        assert!(!roleperm.has(Capability::Ban));
+        assert!(roleperm.allow);
+
+        roleperm.set(Some(Capability::Warn), false);
+        assert!(!roleperm.has(Capability::Warn) && roleperm.has(Capability::Mute));
+        roleperm.set(Some(Capability::Mute), false);
+        assert!(!roleperm.allow);
+    }
+
+    #[test]
+    fn sets_all_capabilities() {
+        let mut roleperm = RolePermission::new(1);
+        roleperm.set(None, true);
+        assert!(Capability::ALL.iter().all(|capability| roleperm.has(*capability)));
+        roleperm.set(None, false);
+        assert!(Capability::ALL.iter().all(|capability| !roleperm.has(*capability)));
+        assert!(!roleperm.allow);
+    }
+
+    #[test]
+    fn legacy_roles_keep_their_grant() {
+        let mut roleperm = RolePermission { role_id: 1, allow: true, capabilities: None };
+        assert!(Capability::ALL.iter().all(|capability| roleperm.has(*capability)));
+        roleperm.set(Some(Capability::Ban), false);
+        assert!(!roleperm.has(Capability::Ban));
+        assert!(roleperm.has(Capability::Warn) && roleperm.has(Capability::RoleSet));
+
+        let denied = RolePermission { role_id: 2, allow: false, capabilities: None };
+        assert!(Capability::ALL.iter().all(|capability| !denied.has(*capability)));
+    }
+}
@@ -0,0 +1,170 @@
+use super::{Command, DBHandler, Delivery, dm_status};
+use crate::discord::{appeal::{AppealId, decision_buttons}, duration::Length, embed::appeal_embed, punishment::Notice, responder::Responder};
+use serde::{Deserialize, Serialize};
+use serenity::{
+    all::{CreateInteractionResponse, CreateInteractionResponseMessage, CreateMessage, EditMessage, PartialMember, User},
+    model::{Timestamp, id::GuildId},
+    prelude::*,
+};
+
+/*
+Appeals are stored on the Profile keyed by punishment id rather than inside the record,
+so the decision is still on file after an approved appeal removes the record.
+*/
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum AppealState {
+    Pending,
+    Approved,
+    Denied,
+    Commuted,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Appeal {
+    pub statement: String,
+    pub submitted: Timestamp,
+    pub state: AppealState,
+    pub moderator: Option<i64>, //Set once decided
+    pub decided: Option<Timestamp>,
+}
+
+async fn respond(command: &Responder, ctx: &Context, content: String) {
+    command
+        .create_response(
+            &ctx.http,
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content(content)
+                    .ephemeral(true),
+            ),
+        )
+        .await
+        .expect("Failed to send response");
+}
+
+impl DBHandler {
+    pub(super) async fn submit_appeal(&mut self, command: Responder, guildid: GuildId, user: User, pid: String, statement: String, ctx: &Context) {
+        let userid = user.id.get() as i64;
+        let Some(mut userprofile) = self.get_profile(userid, &guildid).await else {
+            respond(&command, ctx, "This punishment no longer exists.".to_string()).await;
+            return;
+        };
+        let Some(record) = userprofile.punishments.get(&pid).cloned() else {
+            respond(&command, ctx, "This punishment no longer exists.".to_string()).await;
+            return;
+        };
+        if userprofile.appeals.contains_key(&pid) {
+            respond(&command, ctx, "You have already appealed this punishment.".to_string()).await;
+            return;
+        }
+
+        userprofile.appeals.insert(pid.clone(), Appeal {
+            statement: statement.clone(),
+            submitted: Timestamp::now(),
+            state: AppealState::Pending,
+            moderator: None,
+            decided: None,
+        });
+        self.save_profile(&userprofile, &guildid);
+
+        let queue = self.get_config(&guildid).appeal_channel.unwrap_or(userprofile.user_thread);
+        let content = match queue
+            .send_message(&ctx.http, CreateMessage::new()
+                .embed(appeal_embed(&user, &record, &statement))
+                .components(vec![decision_buttons(guildid, userid, &pid)]))
+            .await
+        {
+            Ok(_) => "Your appeal has been sent to the moderators.",
+            Err(e) => {
+                eprintln!("Error posting appeal in Appeal Submit: {}", e);
+                "Your appeal was saved, but could not be posted for the moderators."
+            }
+        };
+        respond(&command, ctx, content.to_string()).await;
+        println!("User {} appealed punishment {} in guild {}.", userid, pid, guildid);
+    }
+
+    pub(super) async fn decide_appeal(&mut self, command: Responder, id: AppealId, target: (User, Option<PartialMember>), decision: AppealState, length: Option<Length>, ctx: Context) {
+        let (guildid, pid) = (id.guild, id.pid);
+        let invoker = command.user().clone();
+        let idkey = target.0.id.get() as i64;
+        let Some(mut userprofile) = self.get_profile(idkey, &guildid).await else {
+            respond(&command, &ctx, format!("<@{}> lacks any punishment history.", idkey)).await;
+            return;
+        };
+        let Some(record) = userprofile.punishments.get(&pid).cloned() else {
+            respond(&command, &ctx, format!("Punishment {} no longer exists.", pid)).await;
+            return;
+        };
+        match userprofile.appeals.get_mut(&pid) {
+            Some(appeal) if appeal.state == AppealState::Pending => {
+                appeal.state = decision;
+                appeal.moderator = Some(invoker.id.get() as i64);
+                appeal.decided = Some(Timestamp::now());
+            }
+            Some(appeal) => {
+                respond(&command, &ctx, format!("This appeal was already {:?}.", appeal.state)).await;
+                return;
+            }
+            None => {
+                respond(&command, &ctx, format!("No appeal found for punishment {}.", pid)).await;
+                return;
+            }
+        }
+        self.save_profile(&userprofile, &guildid);
+
+        let source = command.source_message();
+        match decision {
+            // PunishRemove only lifts records that are still active and reports a lift that fails, so an approval
+            // for an ended punishment or a member who left just removes the record.
+            AppealState::Approved => {
+                self.handle_punishment(Command::PunishRemove {
+                    command,
+                    targetguild: guildid,
+                    target,
+                    invoker: invoker.clone(),
+                    latest: None,
+                    id: Some(pid),
+                    silent: false,
+                    notify: None,
+                }, ctx.clone()).await;
+            }
+            AppealState::Commuted => {
+                self.handle_punishment(Command::PunishEdit {
+                    command,
+                    targetguild: guildid,
+                    target,
+                    invoker: invoker.clone(),
+                    reason: None,
+                    length,
+                    latest: None,
+                    id: Some(pid),
+                    notify: None,
+                    evidence: Vec::new(),
+                }, ctx.clone()).await;
+            }
+            AppealState::Denied => {
+                let dm = self.get_config(&guildid).dm_default;
+                let delivered = self.notify_user(idkey, &guildid, Notice::AppealDenied, &record, Delivery { moderator: None, dm }, &ctx).await;
+                respond(&command, &ctx, format!("Denied appeal for <@{}>.{}", idkey, dm_status(delivered))).await;
+            }
+            AppealState::Pending => {}
+        }
+
+        // Drop the buttons from the queue entry so it reads as closed.
+        if let Some((channel, message)) = source
+            && let Err(e) = channel
+                .edit_message(&ctx.http, message, EditMessage::new()
+                    .content(format!("{:?} by <@{}>{}",
+                        decision,
+                        invoker.id,
+                        if decision == AppealState::Approved && !record.is_active() { ", the punishment had already ended" } else { "" }))
+                    .components(vec![]))
+                .await
+        {
+            eprintln!("Error closing appeal in Appeal Decide: {}", e);
+        }
+        println!("{} {:?} the appeal of punishment {} for user {} in guild {}.", invoker.name, decision, record.id, idkey, guildid);
+    }
+}
@@ -0,0 +1,85 @@
+use super::GuildConfig;
+use serenity::model::id::GuildId;
+use sha2::{Digest, Sha256};
+use std::{
+    fs,
+    path::PathBuf,
+    time::{Duration, SystemTime},
+};
+
+/*
+Evidence files are archived in server/evidence/<guild>/ named by the SHA-256 of their contents,
+so a screenshot attached to several punishments is only stored once.
+Records keep the hash, so a file pruned by the retention setting shows up as missing rather than broken.
+*/
+
+const ARCHIVE_ROOT: &str = "server/evidence";
+
+pub struct Archive {
+    dir: PathBuf,
+    pub enabled: bool,
+    max_size: u64, //Bytes
+    retention: Option<Duration>, //None keeps files forever
+}
+
+impl Archive {
+    pub fn for_guild(guild: GuildId, config: &GuildConfig) -> Self {
+        Archive {
+            dir: PathBuf::from(ARCHIVE_ROOT).join(guild.to_string()),
+            enabled: config.archive,
+            max_size: config.archive_max_size as u64 * 1024 * 1024,
+            retention: (config.archive_retention > 0).then(|| Duration::from_secs(config.archive_retention as u64 * 86400)),
+        }
+    }
+
+    pub fn store(&self, data: &[u8]) -> Result<String, String> {
+        if data.len() as u64 > self.max_size {
+            return Err(format!("{} bytes is over the archive limit of {} bytes", data.len(), self.max_size));
+        }
+        fs::create_dir_all(&self.dir).map_err(|e| e.to_string())?;
+
+        let hash = format!("{:x}", Sha256::digest(data));
+        let path = self.dir.join(&hash);
+        if path.exists() {
+            // Reused evidence restarts its retention period.
+            fs::File::options()
+                .append(true)
+                .open(&path)
+                .and_then(|file| file.set_modified(SystemTime::now()))
+                .map_err(|e| e.to_string())?;
+        } else {
+            // Written under a temporary name so an interrupted write never passes for the real file.
+            let partial = self.dir.join(format!("{}.partial", hash));
+            fs::write(&partial, data).map_err(|e| e.to_string())?;
+            fs::rename(&partial, &path).map_err(|e| e.to_string())?;
+        }
+        Ok(hash)
+    }
+
+    pub fn load(&self, hash: &str) -> Option<Vec<u8>> {
+        // Hashes come from stored records, but never let one step outside the guild directory.
+        if hash.is_empty() || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        fs::read(self.dir.join(hash)).ok()
+    }
+
+    pub fn prune(&self) {
+        let Some(retention) = self.retention else {
+            return;
+        };
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return;
+        };
+        let cutoff = SystemTime::now() - retention;
+        for entry in entries.flatten() {
+            let expired = entry
+                .metadata()
+                .and_then(|metadata| metadata.modified())
+                .is_ok_and(|modified| modified < cutoff);
+            if expired && let Err(e) = fs::remove_file(entry.path()) {
+                eprintln!("Error pruning archived evidence {:?}: {}", entry.path(), e);
+            }
+        }
+    }
+}
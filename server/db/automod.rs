@@ -0,0 +1,155 @@
+use super::{DBHandler, Profile};
+use crate::discord::{embed::profembed, profile::profile_buttons, thread::create_user_profile};
+use polodb_core::CollectionT;
+use serde::{Deserialize, Serialize};
+use serenity::{
+    all::{PartialMember, User},
+    model::{Timestamp, id::{ChannelId, GuildId}},
+    prelude::*,
+    utils::{FormattedTimestamp, FormattedTimestampStyle},
+};
+use std::collections::BTreeMap;
+
+/*
+AutoMod hits are kept on the profile next to the punishments, but they never count as one.
+Discord sends one execution event per action of a rule, so actions of the same hit are merged into one entry.
+*/
+
+// Oldest hits are dropped past this, the profile document shouldn't grow without bound.
+const MAX_HITS: usize = 100;
+// Execution events of the same hit arrive within moments of each other.
+const MERGE_WINDOW: i64 = 10;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AutoModHit {
+    pub rule_id: u64,
+    pub rule: String, //Rule name, the id when it couldn't be fetched
+    pub matched: Option<String>, //Matched content or keyword, None without the Message Content intent
+    pub content: String,
+    pub actions: Vec<String>,
+    pub channel: Option<ChannelId>,
+    pub time: Timestamp,
+}
+
+impl AutoModHit {
+    pub fn describe(&self) -> String {
+        let mut line = format!("{} **{}** ({})",
+            FormattedTimestamp::new(self.time, Some(FormattedTimestampStyle::ShortDateTime)),
+            self.rule,
+            self.actions.join(", "));
+        if let Some(matched) = &self.matched {
+            line.push_str(&format!(" matched `{}`", matched.replace('`', "'")));
+        }
+        if let Some(channel) = self.channel {
+            line.push_str(&format!(" in <#{}>", channel));
+        }
+        line
+    }
+
+    fn same_hit(&self, other: &AutoModHit) -> bool {
+        self.rule_id == other.rule_id
+            && self.content == other.content
+            && (self.time.unix_timestamp() - other.time.unix_timestamp()).abs() <= MERGE_WINDOW
+    }
+}
+
+impl DBHandler {
+    pub(super) async fn record_automod(&self, guildid: GuildId, target: (User, Option<PartialMember>), hit: AutoModHit, ctx: &Context) {
+        let idkey = target.0.id.get() as i64;
+        let Some(mut profile) = self.profile_or_open(idkey, &guildid, &target, ctx).await else {
+            return;
+        };
+        match profile.automod.last_mut() {
+            Some(last) if last.same_hit(&hit) => {
+                for action in hit.actions {
+                    if !last.actions.contains(&action) {
+                        last.actions.push(action);
+                    }
+                }
+            }
+            _ => profile.automod.push(hit),
+        }
+        if profile.automod.len() > MAX_HITS {
+            let excess = profile.automod.len() - MAX_HITS;
+            profile.automod.drain(..excess);
+        }
+        let bot = User::from((*ctx.cache.current_user()).clone());
+        self.update_profile(&mut profile, &guildid, &target, &bot, ctx).await;
+    }
+
+    pub(super) async fn set_quarantine(&self, guildid: GuildId, target: (User, Option<PartialMember>), quarantined: bool, ctx: &Context) {
+        let idkey = target.0.id.get() as i64;
+        let profile = if quarantined {
+            self.profile_or_open(idkey, &guildid, &target, ctx).await
+        } else {
+            self.get_profile(idkey, &guildid).await
+        };
+        let Some(mut profile) = profile else {
+            return;
+        };
+        if profile.quarantined == quarantined {
+            return;
+        }
+        profile.quarantined = quarantined;
+        println!("Set quarantine of user {} to {} in guild {}.", idkey, quarantined, guildid);
+        let bot = User::from((*ctx.cache.current_user()).clone());
+        self.update_profile(&mut profile, &guildid, &target, &bot, ctx).await;
+    }
+
+    // Profiles are otherwise only opened by a first punishment, this opens one without any.
+    async fn profile_or_open(&self, userid: i64, guildid: &GuildId, target: &(User, Option<PartialMember>), ctx: &Context) -> Option<Profile> {
+        if let Some(profile) = self.get_profile(userid, guildid).await {
+            return Some(profile);
+        }
+        let (log, _) = self.threadlog.get(guildid)?;
+        let guilddb = self.database.get(guildid)?;
+        let bot = User::from((*ctx.cache.current_user()).clone());
+        let embed = profembed(&bot, target, &BTreeMap::new(), &[], false, &self.get_config(guildid)).await;
+        let userthread = match create_user_profile(log, ctx, embed, userid, vec![profile_buttons(*guildid, userid)]).await {
+            Ok(channelid) => channelid,
+            Err(e) => {
+                eprintln!("Error creating user profile thread in AutoMod: {}", e);
+                return None;
+            }
+        };
+        let profile = Profile::new(userid, userthread, BTreeMap::new());
+        if let Err(e) = guilddb.profilecol.insert_one(&profile) {
+            eprintln!("Error creating new profile in AutoMod: {}", e);
+            return None;
+        }
+        Some(profile)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hit(rule_id: u64, content: &str, seconds: i64) -> AutoModHit {
+        AutoModHit {
+            rule_id,
+            rule: "Blocked words".to_string(),
+            matched: None,
+            content: content.to_string(),
+            actions: vec!["Block message".to_string()],
+            channel: None,
+            time: Timestamp::from_unix_timestamp(seconds).unwrap(),
+        }
+    }
+
+    #[test]
+    fn merges_actions_of_one_hit() {
+        let first = hit(1, "spam", 1_700_000_000);
+        assert!(first.same_hit(&hit(1, "spam", 1_700_000_000)));
+        assert!(first.same_hit(&hit(1, "spam", 1_700_000_000 + MERGE_WINDOW)));
+        assert!(first.same_hit(&hit(1, "spam", 1_700_000_000 - MERGE_WINDOW)));
+    }
+
+    #[test]
+    fn keeps_separate_hits_apart() {
+        let first = hit(1, "spam", 1_700_000_000);
+        assert!(!first.same_hit(&hit(2, "spam", 1_700_000_000)));
+        assert!(!first.same_hit(&hit(1, "more spam", 1_700_000_000)));
+        assert!(!first.same_hit(&hit(1, "spam", 1_700_000_001 + MERGE_WINDOW)));
+    }
+}
@@ -0,0 +1,301 @@
+use super::DBHandler;
+use polodb_core::{CollectionT, bson::doc};
+use serde::{Deserialize, Serialize};
+use crate::discord::commands::PunishmentType;
+use serenity::model::id::{ChannelId, GuildId, RoleId};
+
+/*
+One GuildConfig document per guild database. Missing fields fall back to defaults,
+so documents saved by older versions keep loading as new settings are added.
+*/
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct GuildConfig {
+    pub log_channel: Option<ChannelId>,
+    pub notifier_channel: Option<ChannelId>,
+    pub appeal_channel: Option<ChannelId>, //Appeal queue, each user's profile thread when unset
+    pub mute_role: Option<RoleId>,
+    pub dm_default: bool,
+    pub appeal: Option<String>, //Appended to every punishment notice
+    pub archive: bool, //Keep local copies of evidence files
+    pub archive_max_size: u32, //MB per file
+    pub archive_retention: u32, //Days, 0 keeps files forever
+    pub decay: DecayWindows,
+    pub points: PointWeights,
+    pub colors: EmbedColors,
+}
+
+// Days until a record of each type stops counting, 0 never decays.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct DecayWindows {
+    pub warn: u32,
+    pub mute: u32,
+    pub timeout: u32,
+    pub kick: u32,
+    pub softban: u32,
+    pub ban: u32,
+}
+
+// Points a new record of each type carries, see db/points.rs.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct PointWeights {
+    pub warn: u32,
+    pub mute: u32,
+    pub timeout: u32,
+    pub kick: u32,
+    pub softban: u32,
+    pub ban: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct EmbedColors {
+    pub ban: u32,
+    pub mute: u32,
+    pub timeout: u32,
+}
+
+// Settings given to /modconfig set, None leaves the current value.
+#[derive(Default)]
+pub struct ConfigChanges {
+    pub log_channel: Option<ChannelId>,
+    pub notifier_channel: Option<ChannelId>,
+    pub appeal_channel: Option<ChannelId>,
+    pub mute_role: Option<RoleId>,
+    pub dm_default: Option<bool>,
+    pub appeal: Option<String>,
+    pub archive: Option<bool>,
+    pub archive_max_size: Option<i64>,
+    pub archive_retention: Option<i64>,
+    pub warn_decay: Option<i64>,
+    pub mute_decay: Option<i64>,
+    pub timeout_decay: Option<i64>,
+    pub kick_decay: Option<i64>,
+    pub softban_decay: Option<i64>,
+    pub ban_decay: Option<i64>,
+    pub warn_points: Option<i64>,
+    pub mute_points: Option<i64>,
+    pub timeout_points: Option<i64>,
+    pub kick_points: Option<i64>,
+    pub softban_points: Option<i64>,
+    pub ban_points: Option<i64>,
+    pub ban_color: Option<String>,
+    pub mute_color: Option<String>,
+    pub timeout_color: Option<String>,
+}
+
+impl Default for GuildConfig {
+    fn default() -> Self {
+        GuildConfig {
+            log_channel: None,
+            notifier_channel: None,
+            appeal_channel: None,
+            mute_role: None,
+            dm_default: true,
+            appeal: None,
+            archive: false,
+            archive_max_size: 25,
+            archive_retention: 0,
+            decay: DecayWindows::default(),
+            points: PointWeights::default(),
+            colors: EmbedColors::default(),
+        }
+    }
+}
+
+impl Default for PointWeights {
+    fn default() -> Self {
+        PointWeights {
+            warn: 1,
+            mute: 3,
+            timeout: 2,
+            kick: 4,
+            softban: 4,
+            ban: 5,
+        }
+    }
+}
+
+impl Default for EmbedColors {
+    fn default() -> Self {
+        EmbedColors {
+            ban: 0xFF0000,     //Red
+            mute: 0xFF9900,    //Orange
+            timeout: 0xFFE600, //Yellow
+        }
+    }
+}
+
+impl DecayWindows {
+    pub fn days(&self, punishment: &PunishmentType) -> u32 {
+        match punishment {
+            PunishmentType::Warn => self.warn,
+            PunishmentType::Mute => self.mute,
+            PunishmentType::Timeout => self.timeout,
+            PunishmentType::Kick => self.kick,
+            PunishmentType::Softban => self.softban,
+            PunishmentType::Ban => self.ban,
+        }
+    }
+
+    pub fn is_set(&self) -> bool {
+        self.warn > 0 || self.mute > 0 || self.timeout > 0 || self.kick > 0 || self.softban > 0 || self.ban > 0
+    }
+
+    fn describe(&self) -> String {
+        let window = |days: u32| if days == 0 { "never".to_string() } else { format!("{}d", days) };
+        format!("Warn {}, Mute {}, Timeout {}, Kick {}, Softban {}, Ban {}",
+            window(self.warn), window(self.mute), window(self.timeout), window(self.kick), window(self.softban), window(self.ban))
+    }
+}
+
+impl PointWeights {
+    pub fn weight(&self, punishment: &PunishmentType) -> u32 {
+        match punishment {
+            PunishmentType::Warn => self.warn,
+            PunishmentType::Mute => self.mute,
+            PunishmentType::Timeout => self.timeout,
+            PunishmentType::Kick => self.kick,
+            PunishmentType::Softban => self.softban,
+            PunishmentType::Ban => self.ban,
+        }
+    }
+}
+
+impl GuildConfig {
+    pub fn apply(&mut self, changes: ConfigChanges) -> Result<(), String> {
+        // Colors are validated first so a bad value leaves the config untouched.
+        let ban = changes.ban_color.as_deref().map(parse_color).transpose()?;
+        let mute = changes.mute_color.as_deref().map(parse_color).transpose()?;
+        let timeout = changes.timeout_color.as_deref().map(parse_color).transpose()?;
+
+        if let Some(channel) = changes.log_channel {
+            self.log_channel = Some(channel);
+        }
+        if let Some(channel) = changes.notifier_channel {
+            self.notifier_channel = Some(channel);
+        }
+        if let Some(channel) = changes.appeal_channel {
+            self.appeal_channel = Some(channel);
+        }
+        if let Some(role) = changes.mute_role {
+            self.mute_role = Some(role);
+        }
+        if let Some(dm) = changes.dm_default {
+            self.dm_default = dm;
+        }
+        if let Some(appeal) = changes.appeal {
+            self.appeal = (!appeal.trim().eq_ignore_ascii_case("none")).then_some(appeal);
+        }
+        if let Some(archive) = changes.archive {
+            self.archive = archive;
+        }
+        // Ranges are enforced by the command options.
+        if let Some(size) = changes.archive_max_size {
+            self.archive_max_size = size.clamp(1, u32::MAX as i64) as u32;
+        }
+        if let Some(days) = changes.archive_retention {
+            self.archive_retention = days.clamp(0, u32::MAX as i64) as u32;
+        }
+        for (change, setting) in [
+            (changes.warn_decay, &mut self.decay.warn),
+            (changes.mute_decay, &mut self.decay.mute),
+            (changes.timeout_decay, &mut self.decay.timeout),
+            (changes.kick_decay, &mut self.decay.kick),
+            (changes.softban_decay, &mut self.decay.softban),
+            (changes.ban_decay, &mut self.decay.ban),
+            (changes.warn_points, &mut self.points.warn),
+            (changes.mute_points, &mut self.points.mute),
+            (changes.timeout_points, &mut self.points.timeout),
+            (changes.kick_points, &mut self.points.kick),
+            (changes.softban_points, &mut self.points.softban),
+            (changes.ban_points, &mut self.points.ban),
+        ] {
+            if let Some(change) = change {
+                *setting = change.clamp(0, u32::MAX as i64) as u32;
+            }
+        }
+        if let Some(color) = ban {
+            self.colors.ban = color;
+        }
+        if let Some(color) = mute {
+            self.colors.mute = color;
+        }
+        if let Some(color) = timeout {
+            self.colors.timeout = color;
+        }
+        Ok(())
+    }
+
+    pub fn describe(&self) -> String {
+        let channel = |c: Option<ChannelId>| c.map_or("Not set".to_string(), |c| format!("<#{}>", c));
+        format!(
+            "**Log channel:** {}\n**Notifier channel:** {}\n**Appeal queue:** {}\n**Mute role:** {}\n**DM on punishment:** {}\n**Appeal instructions:** {}\n**Evidence archive:** {} (up to {} MB per file, {})\n**Decay:** {}\n**Points:** Warn {}, Mute {}, Timeout {}, Kick {}, Softban {}, Ban {}\n**Colors:** Ban #{:06X}, Mute #{:06X}, Timeout #{:06X}",
+            channel(self.log_channel),
+            channel(self.notifier_channel),
+            self.appeal_channel.map_or("Profile threads".to_string(), |c| format!("<#{}>", c)),
+            self.mute_role.map_or("Not set".to_string(), |r| format!("<@&{}>", r)),
+            if self.dm_default { "✅" } else { "❎" },
+            self.appeal.as_deref().unwrap_or("Not set"),
+            if self.archive { "✅" } else { "❎" },
+            self.archive_max_size,
+            if self.archive_retention == 0 { "kept forever".to_string() } else { format!("kept {} days", self.archive_retention) },
+            self.decay.describe(),
+            self.points.warn,
+            self.points.mute,
+            self.points.timeout,
+            self.points.kick,
+            self.points.softban,
+            self.points.ban,
+            self.colors.ban,
+            self.colors.mute,
+            self.colors.timeout,
+        )
+    }
+}
+
+fn parse_color(color: &str) -> Result<u32, String> {
+    let hex = color.trim().trim_start_matches('#');
+    match u32::from_str_radix(hex, 16) {
+        Ok(value) if hex.len() == 6 => Ok(value),
+        _ => Err(format!("Invalid color `{}`, use a hex value like #FF9900.", color)),
+    }
+}
+
+impl DBHandler {
+    pub(super) fn get_config(&self, guildid: &GuildId) -> GuildConfig {
+        if let Some(guilddb) = self.database.get(guildid) {
+            match guilddb.configcol.find_one(doc! {}) {
+                Ok(Some(config)) => config,
+                Ok(None) => GuildConfig::default(),
+                Err(e) => {
+                    eprintln!("Error retrieving config in Config Query: {}", e);
+                    GuildConfig::default()
+                }
+            }
+        } else {
+            eprintln!("No database found for queried guild in Config Query");
+            GuildConfig::default()
+        }
+    }
+
+    pub(super) fn save_config(&mut self, guildid: &GuildId, config: &GuildConfig) {
+        if let (Some(log), Some(notifier)) = (config.log_channel, config.notifier_channel) {
+            self.threadlog.insert(*guildid, (log, notifier));
+        }
+        if let Some(guilddb) = self.database.get(guildid) {
+            // Single document collection, replaced whole on every save.
+            if let Err(e) = guilddb.configcol.delete_many(doc! {}) {
+                eprintln!("Error clearing config in Config Save: {}", e);
+            }
+            if let Err(e) = guilddb.configcol.insert_one(config) {
+                eprintln!("Error saving config in Config Save: {}", e);
+            }
+        } else {
+            eprintln!("No database found for queried guild in Config Save");
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use super::{DBHandler, Profile};
+use polodb_core::{CollectionT, bson::doc};
+use serenity::{
+    all::{PartialMember, User},
+    model::{Timestamp, id::{GuildId, UserId}},
+    prelude::*,
+};
+
+/*
+Records decay after the window set per type in GuildConfig::decay. The sweep runs as a recurring job per guild,
+marks due records as expired and refreshes the profile thread. Expired records stay in the history,
+but no longer count towards the profile summary or escalation rules. Profiles whose point total dropped are refreshed too.
+*/
+
+pub(super) const SWEEP_INTERVAL: i64 = 3600;
+
+impl DBHandler {
+    pub(super) async fn sweep_decay(&self, guildid: GuildId, ctx: &Context) {
+        let config = self.get_config(&guildid);
+        let decay = &config.decay;
+        // Points only fade with a decay window, so there is nothing to refresh without one.
+        if !decay.is_set() {
+            return;
+        }
+        let profiles = if let Some(guilddb) = self.database.get(&guildid) {
+            match guilddb.profilecol.find(doc! {}).run() {
+                Ok(cursor) => cursor.filter_map(|profile| profile.ok()).collect::<Vec<Profile>>(),
+                Err(e) => {
+                    eprintln!("Error retrieving profiles in Decay Sweep: {}", e);
+                    return;
+                }
+            }
+        } else {
+            eprintln!("No database found for queried guild in Decay Sweep");
+            return;
+        };
+
+        let now = Timestamp::now();
+        let bot = User::from((*ctx.cache.current_user()).clone());
+        for mut profile in profiles {
+            let mut expired = vec![];
+            for record in profile.punishments.values_mut() {
+                if record.decay_due(decay.days(&record.punishment)).is_some_and(|due| due <= now) {
+                    record.expired = Some(now);
+                    expired.push(record.id.clone());
+                }
+            }
+            if !profile.refresh_points(&config) && expired.is_empty() {
+                continue;
+            }
+            if !expired.is_empty() {
+                println!("Expired punishments {} for user {} in guild {}.", expired.join(", "), profile.user_id, guildid);
+            }
+
+            match UserId::new(profile.user_id as u64).to_user(ctx).await {
+                Ok(user) => {
+                    let member = guildid.member(&ctx.http, user.id).await.ok().map(PartialMember::from);
+                    self.update_profile(&mut profile, &guildid, &(user, member), &bot, ctx).await;
+                }
+                Err(e) => {
+                    // The records are still marked, the thread catches up on the next update.
+                    eprintln!("Error fetching user in Decay Sweep: {}", e);
+                    self.save_profile(&profile, &guildid);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,340 @@
+use super::{DBHandler, Delivery, GuildConfig, Profile, PunishmentRecord, RecordDetails, point_total};
+use crate::discord::{commands::PunishmentType, duration::{format_duration, parse_duration, Length, MAX_TIMEOUT}};
+use polodb_core::{CollectionT, bson::doc};
+use serde::{Deserialize, Serialize};
+use serenity::{
+    all::{PartialMember, User},
+    model::{Timestamp, id::GuildId},
+    prelude::*,
+};
+
+/*
+Escalation ladders are stored per guild in the "Escalation" collection, one document per rule.
+A rule either counts punishments of one type, or is a point threshold that is met when a punishment takes the
+user's point total up to it. Both are steps of a ladder and fire once when reached: a count rule matches only the punishment
+that makes the count exactly N, so later punishments of the type don't stack another automatic one (add a rule per step instead). After a punishment is added, the matching rule is applied as an automatic punishment,
+preferring the highest threshold and then the highest count, and it is recorded with the rule and the punishment that triggered it.
+*/
+
+// Automatic punishments can meet further rules, this bounds a ladder that feeds itself (i.e. warn -> warn).
+const MAX_STEPS: usize = 3;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EscalationRule {
+    pub rule_id: String,
+    pub trigger: Option<PunishmentType>, //None for point thresholds
+    #[serde(default)]
+    pub count: u32,
+    #[serde(default)]
+    pub points: Option<u32>,
+    pub within: Option<i64>, //Seconds, None counts the whole history
+    pub action: PunishmentType,
+    pub duration: Option<i64>, //Seconds, None is permanent
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AutoAction {
+    pub rule: String,
+    pub trigger: String, //Punishment id that met the rule
+}
+
+impl EscalationRule {
+    // The rule id is assigned when the rule is saved.
+    pub fn new(trigger: PunishmentType, count: i64, within: Option<&str>, action: PunishmentType, duration: Option<&str>) -> Result<Self, String> {
+        let within = match within.map(parse_duration).transpose()? {
+            Some(Length::Timed(seconds)) => Some(seconds),
+            Some(Length::Permanent) | None => None,
+            Some(_) => return Err("The within window must be a length like 30d, not an end time.".to_string()),
+        };
+        Ok(EscalationRule {
+            rule_id: String::new(),
+            trigger: Some(trigger),
+            count: count.clamp(1, u32::MAX as i64) as u32,
+            points: None,
+            within,
+            duration: EscalationRule::action_duration(&action, duration)?,
+            action,
+        })
+    }
+
+    pub fn threshold(points: i64, action: PunishmentType, duration: Option<&str>) -> Result<Self, String> {
+        Ok(EscalationRule {
+            rule_id: String::new(),
+            trigger: None,
+            count: 0,
+            points: Some(points.clamp(1, u32::MAX as i64) as u32),
+            within: None,
+            duration: EscalationRule::action_duration(&action, duration)?,
+            action,
+        })
+    }
+
+    fn action_duration(action: &PunishmentType, duration: Option<&str>) -> Result<Option<i64>, String> {
+        let duration = match duration.map(parse_duration).transpose()? {
+            _ if *action == PunishmentType::Warn || action.is_instant() => None,
+            Some(Length::Timed(seconds)) => Some(seconds),
+            Some(Length::Permanent) | None => None,
+            Some(_) => return Err("The duration must be a length like 1h, not an end time.".to_string()),
+        };
+        if *action == PunishmentType::Timeout && duration.is_none_or(|seconds| seconds > MAX_TIMEOUT) {
+            return Err("Timeouts need a duration within 28 days.".to_string());
+        }
+        Ok(duration)
+    }
+
+    pub fn length(&self) -> Length {
+        if self.action.is_instant() {
+            return Length::Timed(0);
+        }
+        self.duration.map_or(Length::Permanent, Length::Timed)
+    }
+
+    // Counts the unexpired records of the trigger type inside the window, including the one just added, and matches on reaching the count.
+    // Thresholds only match the punishment that crosses them, not every punishment after.
+    fn matches(&self, profile: &Profile, record: &PunishmentRecord, config: &GuildConfig) -> bool {
+        let Some(trigger) = &self.trigger else {
+            let Some(points) = self.points else {
+                return false;
+            };
+            let now = Timestamp::now();
+            let total = point_total(&profile.punishments, config, now);
+            return total >= points && total - record.current_points(config, now).min(total) < points;
+        };
+        if record.punishment != *trigger {
+            return false;
+        }
+        let since = self.within.map_or(i64::MIN, |within| Timestamp::now().unix_timestamp() - within);
+        let count = profile.punishments
+            .values()
+            .filter(|past| past.punishment == *trigger && past.expired.is_none() && past.punished_for.0.unix_timestamp() >= since)
+            .count();
+        count == self.count as usize
+    }
+
+    // The rule the latest punishment meets, as long as the ladder may take another step.
+    fn next_step<'a>(rules: &'a [EscalationRule], profile: &Profile, trigger: &PunishmentRecord, config: &GuildConfig, taken: usize) -> Option<&'a EscalationRule> {
+        if taken >= MAX_STEPS {
+            return None;
+        }
+        rules
+            .iter()
+            .filter(|rule| rule.matches(profile, trigger, config))
+            .max_by_key(|rule| (rule.points, rule.count))
+    }
+
+    pub fn describe(&self) -> String {
+        let condition = match (&self.trigger, self.points) {
+            (Some(trigger), _) => format!("{} {:?} {}",
+                self.count,
+                trigger,
+                self.within.map_or("in total".to_string(), |within| format!("within {}", format_duration(within)))),
+            (None, Some(points)) => format!("{} points", points),
+            (None, None) => "Never".to_string(),
+        };
+        format!("`{}` - {} → {:?} {}",
+            self.rule_id,
+            condition,
+            self.action,
+            match (&self.action, self.duration) {
+                (PunishmentType::Warn | PunishmentType::Kick | PunishmentType::Softban, _) => String::new(),
+                (_, Some(duration)) => format!("for {}", format_duration(duration)),
+                (_, None) => "permanently".to_string(),
+            })
+    }
+}
+
+impl DBHandler {
+    pub(super) fn list_escalations(&self, guildid: &GuildId) -> Vec<EscalationRule> {
+        if let Some(guilddb) = self.database.get(guildid) {
+            match guilddb.escalationcol.find(doc! {}).run() {
+                Ok(cursor) => {
+                    let mut rules = cursor.filter_map(|rule| rule.ok()).collect::<Vec<EscalationRule>>();
+                    rules.sort_by_key(|rule| rule.rule_id.parse::<u32>().unwrap_or(0));
+                    rules
+                }
+                Err(e) => {
+                    eprintln!("Error retrieving rules in Escalation List: {}", e);
+                    Vec::new()
+                }
+            }
+        } else {
+            eprintln!("No database found for queried guild in Escalation List");
+            Vec::new()
+        }
+    }
+
+    pub(super) fn add_escalation(&self, guildid: &GuildId, mut rule: EscalationRule) -> Option<EscalationRule> {
+        let next = self.list_escalations(guildid)
+            .iter()
+            .filter_map(|rule| rule.rule_id.parse::<u32>().ok())
+            .max()
+            .unwrap_or(0) + 1;
+        rule.rule_id = next.to_string();
+        let guilddb = self.database.get(guildid)?;
+        match guilddb.escalationcol.insert_one(&rule) {
+            Ok(_) => Some(rule),
+            Err(e) => {
+                eprintln!("Error saving rule in Escalation Add: {}", e);
+                None
+            }
+        }
+    }
+
+    pub(super) fn remove_escalation(&self, guildid: &GuildId, rule_id: &str) -> bool {
+        match self.database.get(guildid) {
+            Some(guilddb) => match guilddb.escalationcol.delete_one(doc! { "rule_id": rule_id }) {
+                Ok(result) => result.deleted_count > 0,
+                Err(e) => {
+                    eprintln!("Error removing rule in Escalation Remove: {}", e);
+                    false
+                }
+            },
+            None => false,
+        }
+    }
+
+    // Returns the automatic punishments that were applied, in order.
+    pub(super) async fn escalate(&mut self, guildid: GuildId, target: &(User, Option<PartialMember>), record: &PunishmentRecord, ctx: &Context) -> Vec<PunishmentRecord> {
+        let rules = self.list_escalations(&guildid);
+        if rules.is_empty() {
+            return Vec::new();
+        }
+        let idkey = target.0.id.get() as i64;
+        let bot = User::from((*ctx.cache.current_user()).clone());
+        let config = self.get_config(&guildid);
+        let mut trigger = record.clone();
+        let mut applied = vec![];
+        loop {
+            let Some(mut profile) = self.get_profile(idkey, &guildid).await else {
+                break;
+            };
+            let Some(rule) = EscalationRule::next_step(&rules, &profile, &trigger, &config, applied.len()) else {
+                break;
+            };
+            let now = Timestamp::now();
+            let Ok(end) = rule.length().end_from(now) else {
+                eprintln!("Error converting timestamp for rule {} in Escalation", rule.rule_id);
+                break;
+            };
+
+            let reason = format!("Automatic escalation: {}", rule.describe());
+            let details = RecordDetails {
+                reason: Some(reason),
+                punished_for: (now, end),
+                points: config.points.weight(&rule.action),
+                delete_days: rule.action.default_delete_days(),
+                ..Default::default()
+            };
+            let (profile, mut step) = profile.add_punishment(rule.action.clone(), bot.id.get() as i64, details);
+            step.automatic = Some(AutoAction { rule: rule.rule_id.clone(), trigger: trigger.id.clone() });
+            profile.punishments.insert(step.id.clone(), step.clone());
+            self.update_profile(profile, &guildid, target, &bot, ctx).await;
+
+            let dm = config.dm_default;
+            self.enforce(guildid, target, &step, Delivery { moderator: None, dm }, ctx).await;
+            println!("Escalated user {} to {:?} by rule {} in guild {}.", idkey, step.punishment, rule.rule_id, guildid);
+
+            let banned = step.punishment == PunishmentType::Ban;
+            applied.push(step.clone());
+            if banned {
+                break;
+            }
+            trigger = step;
+        }
+        applied
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::fixtures::{DAY, now, profile, record};
+
+    fn warns(count: usize) -> Profile {
+        profile((0..count).map(|_| record(PunishmentType::Warn, now() - 60, None)).collect())
+    }
+
+    fn latest(profile: &Profile) -> PunishmentRecord {
+        profile.punishments[&profile.latest_id().unwrap()].clone()
+    }
+
+    fn matches(rule: &EscalationRule, profile: &Profile) -> bool {
+        rule.matches(profile, &latest(profile), &GuildConfig::default())
+    }
+
+    #[test]
+    fn count_rules_fire_only_on_reaching_the_count() {
+        let rule = EscalationRule::new(PunishmentType::Warn, 3, None, PunishmentType::Kick, None).unwrap();
+        assert!(!matches(&rule, &warns(2)));
+        assert!(matches(&rule, &warns(3)));
+        assert!(!matches(&rule, &warns(4)));
+    }
+
+    #[test]
+    fn count_rules_only_count_their_trigger() {
+        let rule = EscalationRule::new(PunishmentType::Warn, 2, None, PunishmentType::Kick, None).unwrap();
+        let mixed = profile(vec![
+            record(PunishmentType::Warn, now() - 60, None),
+            record(PunishmentType::Mute, now() - 60, None),
+        ]);
+        assert!(!matches(&rule, &mixed));
+        let mut expired = warns(2);
+        expired.punishments.get_mut("1").unwrap().expired = Some(Timestamp::now());
+        assert!(!matches(&rule, &expired));
+    }
+
+    #[test]
+    fn count_rules_only_count_inside_the_window() {
+        let rule = EscalationRule::new(PunishmentType::Warn, 2, Some("1d"), PunishmentType::Kick, None).unwrap();
+        let spread = profile(vec![
+            record(PunishmentType::Warn, now() - 2 * DAY, None),
+            record(PunishmentType::Warn, now() - 60, None),
+        ]);
+        assert!(!matches(&rule, &spread));
+        let close = profile(vec![
+            record(PunishmentType::Warn, now() - 3600, None),
+            record(PunishmentType::Warn, now() - 60, None),
+        ]);
+        assert!(matches(&rule, &close));
+    }
+
+    #[test]
+    fn thresholds_fire_only_when_crossed() {
+        // Warnings weigh 1 point by default.
+        let rule = EscalationRule::threshold(5, PunishmentType::Ban, None).unwrap();
+        assert!(!matches(&rule, &warns(4)));
+        assert!(matches(&rule, &warns(5)));
+        assert!(!matches(&rule, &warns(6)));
+    }
+
+    #[test]
+    fn prefers_thresholds_then_higher_counts() {
+        let config = GuildConfig::default();
+        let recent = EscalationRule::new(PunishmentType::Warn, 1, Some("1h"), PunishmentType::Timeout, Some("1h")).unwrap();
+        let total = EscalationRule::new(PunishmentType::Warn, 3, None, PunishmentType::Mute, None).unwrap();
+        let threshold = EscalationRule::threshold(3, PunishmentType::Kick, None).unwrap();
+        let profile = profile(vec![
+            record(PunishmentType::Warn, now() - 2 * DAY, None),
+            record(PunishmentType::Warn, now() - 2 * DAY, None),
+            record(PunishmentType::Warn, now() - 60, None),
+        ]);
+        let trigger = latest(&profile);
+
+        let rules = [recent.clone(), total.clone()];
+        let step = EscalationRule::next_step(&rules, &profile, &trigger, &config, 0);
+        assert_eq!(step.map(|rule| &rule.action), Some(&PunishmentType::Mute));
+        let rules = [recent, total, threshold];
+        let step = EscalationRule::next_step(&rules, &profile, &trigger, &config, 0);
+        assert_eq!(step.map(|rule| &rule.action), Some(&PunishmentType::Kick));
+    }
+
+    #[test]
+    fn ladders_stop_after_max_steps() {
+        let config = GuildConfig::default();
+        let rules = [EscalationRule::new(PunishmentType::Warn, 1, None, PunishmentType::Warn, None).unwrap()];
+        let profile = warns(1);
+        let trigger = latest(&profile);
+        assert!(EscalationRule::next_step(&rules, &profile, &trigger, &config, MAX_STEPS - 1).is_some());
+        assert!(EscalationRule::next_step(&rules, &profile, &trigger, &config, MAX_STEPS).is_none());
+    }
+}
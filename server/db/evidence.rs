@@ -0,0 +1,269 @@
+use super::{archive::Archive, DBHandler, PunishmentRecord};
+use serde::{Deserialize, Serialize};
+use serenity::{
+    all::{CommandInteraction, CreateAttachment, CreateInteractionResponse, CreateInteractionResponseMessage, CreateMessage, PartialMember, User},
+    model::{Timestamp, channel::{Attachment, Message}, id::{ChannelId, GuildId}},
+    prelude::*,
+    utils::{FormattedTimestamp, FormattedTimestampStyle},
+};
+
+/*
+Evidence is copied onto the PunishmentRecord when the punishment is issued,
+so the proof stays on file after the original message is deleted.
+Attachments are re-uploaded into the profile thread, as the URLs Discord hands out for
+the interaction expire. With the archive on, files are also kept locally, see archive.rs.
+*/
+
+// Longest message excerpt shown in a profile embed, the full content stays in the database.
+const EXCERPT_LENGTH: usize = 200;
+// Discord's limits for a single message.
+const MAX_FILES: usize = 10;
+const MAX_CONTENT: usize = 1990;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub enum Evidence {
+    Message(MessageSnapshot),
+    Attachment(AttachmentEvidence),
+    Link(String), //Message link that couldn't be fetched when given
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MessageSnapshot {
+    pub channel: ChannelId,
+    pub content: String,
+    pub attachments: Vec<String>, //URLs
+    pub link: String,
+    pub sent: Timestamp,
+    #[serde(default)]
+    pub archived: Vec<ArchivedFile>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ArchivedFile {
+    pub filename: String,
+    pub hash: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AttachmentEvidence {
+    pub url: String,
+    pub filename: String,
+    pub content_type: Option<String>,
+    pub link: Option<String>, //Message in the profile thread holding the re-upload
+    #[serde(default)]
+    pub archived: Option<String>, //Hash in the evidence archive
+}
+
+impl AttachmentEvidence {
+    pub fn from_attachment(attachment: &Attachment) -> Self {
+        AttachmentEvidence {
+            url: attachment.url.clone(),
+            filename: attachment.filename.clone(),
+            content_type: attachment.content_type.clone(),
+            link: None,
+            archived: None,
+        }
+    }
+}
+
+impl MessageSnapshot {
+    pub fn from_message(message: &Message, guild: GuildId) -> Self {
+        MessageSnapshot {
+            channel: message.channel_id,
+            content: message.content.clone(),
+            attachments: message.attachments.iter().map(|attachment| attachment.url.clone()).collect(),
+            link: message.id.link(message.channel_id, Some(guild)),
+            sent: message.timestamp,
+            archived: Vec::new(),
+        }
+    }
+}
+
+impl Evidence {
+    pub fn describe(&self) -> String {
+        match self {
+            Evidence::Message(snapshot) => {
+                let mut excerpt = snapshot.content.chars().take(EXCERPT_LENGTH).collect::<String>();
+                if snapshot.content.chars().count() > EXCERPT_LENGTH {
+                    excerpt.push('…');
+                }
+                let mut description = format!("[Message]({}) in <#{}> {}",
+                    snapshot.link,
+                    snapshot.channel,
+                    FormattedTimestamp::new(snapshot.sent, Some(FormattedTimestampStyle::ShortDateTime)));
+                if !excerpt.is_empty() {
+                    description.push_str(&format!("\n> {}", excerpt.replace('\n', "\n> ")));
+                }
+                if !snapshot.attachments.is_empty() {
+                    let links = snapshot.attachments
+                        .iter()
+                        .enumerate()
+                        .map(|(i, url)| format!("[{}]({})", i + 1, url))
+                        .collect::<Vec<String>>()
+                        .join(" ");
+                    description.push_str(&format!("\nAttachments: {}", links));
+                }
+                description
+            }
+            Evidence::Attachment(attachment) => {
+                let mut description = format!("[{}]({})", attachment.filename, attachment.link.as_ref().unwrap_or(&attachment.url));
+                if let Some(content_type) = &attachment.content_type {
+                    description.push_str(&format!(" ({})", content_type));
+                }
+                description
+            }
+            Evidence::Link(link) => format!("[Message]({}) (unavailable when added)", link),
+        }
+    }
+}
+
+impl DBHandler {
+    // Re-uploads new attachments into the thread and archives evidence files when the guild has the archive on.
+    pub(super) async fn store_evidence(&self, guildid: &GuildId, thread: ChannelId, record: &mut PunishmentRecord, ctx: &Context) -> bool {
+        let archive = Archive::for_guild(*guildid, &self.get_config(guildid));
+        let archive = archive.enabled.then_some(&archive);
+        let store = |data: &[u8], filename: &str| match archive?.store(data) {
+            Ok(hash) => Some(hash),
+            Err(e) => {
+                eprintln!("Error archiving evidence {} in Evidence Store: {}", filename, e);
+                None
+            }
+        };
+
+        let mut changed = false;
+        let mut files = vec![];
+        let mut pending = vec![];
+        for (i, evidence) in record.evidence.iter_mut().enumerate() {
+            match evidence {
+                Evidence::Attachment(attachment) => {
+                    let rehost = attachment.link.is_none();
+                    let unarchived = archive.is_some() && attachment.archived.is_none();
+                    if !rehost && !unarchived {
+                        continue;
+                    }
+                    match CreateAttachment::url(&ctx.http, &attachment.url).await {
+                        Ok(mut file) => {
+                            if unarchived {
+                                attachment.archived = store(&file.data, &attachment.filename);
+                                changed |= attachment.archived.is_some();
+                            }
+                            if rehost {
+                                file.filename = attachment.filename.clone();
+                                files.push(file);
+                                pending.push(i);
+                            }
+                        }
+                        Err(e) => eprintln!("Error downloading evidence {} in Evidence Store: {}", attachment.filename, e),
+                    }
+                }
+                Evidence::Message(snapshot) if archive.is_some() && snapshot.archived.is_empty() => {
+                    for url in &snapshot.attachments {
+                        match CreateAttachment::url(&ctx.http, url).await {
+                            Ok(file) => {
+                                if let Some(hash) = store(&file.data, &file.filename) {
+                                    snapshot.archived.push(ArchivedFile { filename: file.filename, hash });
+                                    changed = true;
+                                }
+                            }
+                            Err(e) => eprintln!("Error downloading message attachment in Evidence Store: {}", e),
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        if let Some(archive) = archive {
+            archive.prune();
+        }
+        if files.is_empty() {
+            return changed;
+        }
+
+        let message = match thread
+            .send_message(&ctx.http, CreateMessage::new()
+                .content(format!("Evidence for punishment {}", record.id))
+                .add_files(files))
+            .await
+        {
+            Ok(message) => message,
+            Err(e) => {
+                eprintln!("Error uploading evidence in Evidence Store: {}", e);
+                return changed;
+            }
+        };
+        let link = message.link();
+        for (i, uploaded) in pending.into_iter().zip(message.attachments.iter()) {
+            if let Evidence::Attachment(attachment) = &mut record.evidence[i] {
+                attachment.url = uploaded.url.clone();
+                attachment.link = Some(link.clone());
+            }
+        }
+        true
+    }
+
+    pub(super) async fn get_evidence(&self, command: CommandInteraction, guildid: GuildId, target: (User, Option<PartialMember>), pid: String, ctx: &Context) {
+        let idkey = target.0.id.get() as i64;
+        let record = self
+            .get_profile(idkey, &guildid)
+            .await
+            .and_then(|profile| profile.punishments.get(&pid).cloned());
+        let Some(record) = record else {
+            respond(&command, ctx, format!("<@{}> has no punishment {}.", idkey, pid), vec![]).await;
+            return;
+        };
+        if record.evidence.is_empty() {
+            respond(&command, ctx, format!("Punishment {} for <@{}> has no evidence.", pid, idkey), vec![]).await;
+            return;
+        }
+
+        let archived = record.evidence
+            .iter()
+            .flat_map(|evidence| match evidence {
+                Evidence::Attachment(attachment) => attachment.archived
+                    .iter()
+                    .map(|hash| ArchivedFile { filename: attachment.filename.clone(), hash: hash.clone() })
+                    .collect(),
+                Evidence::Message(snapshot) => snapshot.archived.clone(),
+                Evidence::Link(_) => vec![],
+            })
+            .collect::<Vec<ArchivedFile>>();
+        // Still served after archiving is turned off, the files are only removed by retention.
+        let archive = Archive::for_guild(guildid, &self.get_config(&guildid));
+        let files = archived
+            .iter()
+            .filter_map(|file| archive.load(&file.hash).map(|data| CreateAttachment::bytes(data, file.filename.clone())))
+            .take(MAX_FILES)
+            .collect::<Vec<CreateAttachment>>();
+
+        let mut content = format!("Evidence for punishment {} of <@{}>:\n{}",
+            pid,
+            idkey,
+            record.evidence.iter().map(|evidence| format!("- {}", evidence.describe())).collect::<Vec<String>>().join("\n"));
+        if archived.is_empty() {
+            content.push_str("\n\nNothing was archived for this punishment.");
+        } else if files.len() < archived.len() {
+            content.push_str(&format!("\n\n{} of {} archived files could not be loaded or were pruned.", archived.len() - files.len(), archived.len()));
+        }
+        respond(&command, ctx, content, files).await;
+    }
+}
+
+async fn respond(command: &CommandInteraction, ctx: &Context, content: String, files: Vec<CreateAttachment>) {
+    // Excerpts can push a long evidence list past the message limit.
+    let content = match content.char_indices().nth(MAX_CONTENT) {
+        Some((end, _)) => format!("{}…", &content[..end]),
+        None => content,
+    };
+    command
+        .create_response(
+            &ctx.http,
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content(content)
+                    .add_files(files)
+                    .ephemeral(true),
+            ),
+        )
+        .await
+        .expect("Failed to send response");
+}
@@ -0,0 +1,132 @@
+use super::{DBHandler, Profile, PunishmentRecord, RecordDetails, Temporary};
+use crate::discord::{commands::PunishmentType, thread::note_thread};
+use serenity::{
+    all::{PartialMember, User},
+    model::{Timestamp, id::GuildId},
+    prelude::*,
+    utils::{FormattedTimestamp, FormattedTimestampStyle},
+};
+
+/*
+Bans and timeouts given through Discord itself are recorded like /punish, attributed from the audit log by ClientHandler.
+The bot's own actions raise the same events, ClientHandler skips those it can attribute and a change that already matches the profile is ignored.
+*/
+
+#[derive(Debug, Clone, Copy)]
+pub enum ExternalChange {
+    Ban,
+    Unban,
+    Timeout(Timestamp), //New end
+    TimeoutRemoved,
+}
+
+impl ExternalChange {
+    pub fn punishment(&self) -> PunishmentType {
+        match self {
+            ExternalChange::Ban | ExternalChange::Unban => PunishmentType::Ban,
+            ExternalChange::Timeout(_) | ExternalChange::TimeoutRemoved => PunishmentType::Timeout,
+        }
+    }
+}
+
+impl DBHandler {
+    pub(super) async fn ingest_external(&mut self, guildid: GuildId, target: (User, Option<PartialMember>), change: ExternalChange, moderator: Option<User>, reason: Option<String>, ctx: &Context) {
+        let idkey = target.0.id.get() as i64;
+        let bot = User::from((*ctx.cache.current_user()).clone());
+        let attribution = moderator.as_ref().map_or("an unknown moderator".to_string(), |moderator| format!("<@{}>", moderator.id));
+        let invoker = moderator.unwrap_or(bot);
+        let ptype = change.punishment();
+        let profile = self.get_profile(idkey, &guildid).await;
+        let active = profile
+            .as_ref()
+            .map(|profile| profile.punishments
+                .values()
+                .filter(|record| record.punishment == ptype && record.is_active())
+                .cloned()
+                .collect::<Vec<PunishmentRecord>>())
+            .unwrap_or_default();
+
+        match change {
+            ExternalChange::Ban | ExternalChange::Timeout(_) => {
+                let end = match change {
+                    ExternalChange::Timeout(until) => until,
+                    _ => Timestamp::default(),
+                };
+                // A ban on file is kept as it is, it may be a temporary ban the bot gave. Only timeouts move an end.
+                if matches!(change, ExternalChange::Ban) && !active.is_empty() {
+                    return;
+                }
+                // Discord drops sub-second precision, so ends are compared in whole seconds.
+                if active.iter().any(|record| record.punished_for.1.unix_timestamp() == end.unix_timestamp()) {
+                    return;
+                }
+                if let (Some(mut profile), Some(record)) = (profile, active.first()) {
+                    // A timeout changed in Discord moves the end of the one already on file.
+                    self.move_end(&mut profile, &guildid, &record.id, end).await;
+                    self.update_profile(&mut profile, &guildid, &target, &invoker, ctx).await;
+                    self.note_external(&profile, format!("⏱️ {:?} (ID {}) was changed outside Modbot by {} to end {}.",
+                        ptype, record.id, attribution, FormattedTimestamp::new(end, Some(FormattedTimestampStyle::ShortDateTime))), ctx).await;
+                    return;
+                }
+                let details = RecordDetails { reason, punished_for: (Timestamp::now(), end), ..Default::default() };
+                let Some(record) = self.process_punishment(&invoker, &target, ptype.clone(), details, &guildid, ctx).await else {
+                    return;
+                };
+                if !record.is_permanent() {
+                    self.add_temporary(guildid, Temporary {
+                        user_id: idkey,
+                        punishment: record.clone(),
+                        negdur: !Timestamp::now().unix_timestamp(),
+                    }).await;
+                }
+                println!("Recorded {:?} given outside Modbot to user {} in guild {}.", ptype, idkey, guildid);
+                if let Some(profile) = self.get_profile(idkey, &guildid).await {
+                    self.note_external(&profile, format!("🔨 {:?} (ID {}) was given outside Modbot by {}.", ptype, record.id, attribution), ctx).await;
+                    self.refresh_punished(guildid, &profile).await;
+                }
+            }
+            ExternalChange::Unban | ExternalChange::TimeoutRemoved => {
+                let Some(mut profile) = profile else {
+                    return;
+                };
+                if active.is_empty() {
+                    return;
+                }
+                let now = Timestamp::now();
+                for record in &active {
+                    self.move_end(&mut profile, &guildid, &record.id, now).await;
+                }
+                self.update_profile(&mut profile, &guildid, &target, &invoker, ctx).await;
+                println!("Closed {:?} lifted outside Modbot for user {} in guild {}.", ptype, idkey, guildid);
+                let ids = active.iter().map(|record| record.id.clone()).collect::<Vec<String>>().join(", ");
+                self.note_external(&profile, format!("🔓 {:?} (ID {}) was lifted outside Modbot by {}.", ptype, ids, attribution), ctx).await;
+                self.refresh_punished(guildid, &profile).await;
+            }
+        }
+    }
+
+    // Sets a new end on a record and reschedules its expiry, an end in the past closes it.
+    // The profile is saved by the caller, once for all records it moved.
+    async fn move_end(&mut self, profile: &mut Profile, guildid: &GuildId, pid: &str, end: Timestamp) {
+        let Some(record) = profile.punishments.get_mut(pid) else {
+            return;
+        };
+        record.punished_for.1 = end;
+        record.expired = None;
+        let record = record.clone();
+        self.remove_temporary(profile.user_id, guildid, pid).await;
+        if record.is_active() && !record.is_permanent() {
+            self.add_temporary(*guildid, Temporary {
+                user_id: profile.user_id,
+                punishment: record,
+                negdur: !Timestamp::now().unix_timestamp(),
+            }).await;
+        }
+    }
+
+    async fn note_external(&self, profile: &Profile, note: String, ctx: &Context) {
+        if let Err(e) = note_thread(ctx, &profile.user_thread, note).await {
+            eprintln!("Error noting outside action in External: {}", e);
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use super::{Profile, PunishmentRecord, RecordDetails};
+use crate::discord::commands::PunishmentType;
+use serenity::model::{Timestamp, id::ChannelId};
+
+/*
+Builders shared by the db tests. Times are unix seconds, tests that depend on the clock build them from now().
+*/
+
+pub const DAY: i64 = 86400;
+
+pub fn at(seconds: i64) -> Timestamp {
+    Timestamp::from_unix_timestamp(seconds).unwrap()
+}
+
+pub fn now() -> i64 {
+    Timestamp::now().unix_timestamp()
+}
+
+// A record from start to end, None is permanent. Points are left unset, so the config weight applies.
+pub fn record(punishment: PunishmentType, start: i64, end: Option<i64>) -> PunishmentRecord {
+    let mut record = PunishmentRecord::new("1".to_string(), punishment, 0, RecordDetails {
+        punished_for: (at(start), end.map_or(Timestamp::default(), at)),
+        ..Default::default()
+    });
+    record.points = None;
+    record
+}
+
+// Records are numbered in the order given, like add_punishment would.
+pub fn profile(records: Vec<PunishmentRecord>) -> Profile {
+    let punishments = records
+        .into_iter()
+        .enumerate()
+        .map(|(i, mut record)| {
+            record.id = (i + 1).to_string();
+            (record.id.clone(), record)
+        })
+        .collect();
+    Profile::new(1, ChannelId::new(1), punishments)
+}
@@ -0,0 +1,107 @@
+use super::{GuildConfig, Profile, PunishmentRecord};
+use serenity::model::Timestamp;
+use std::collections::BTreeMap;
+
+/*
+Each record carries the point weight of its type at the time it was given, older records fall back to the current weight.
+Points fade linearly over the decay window of the type, starting where decay_due starts counting,
+so a record is worth nothing by the time the sweep marks it expired. Types without a window keep their full weight.
+*/
+
+impl PunishmentRecord {
+    pub fn weight(&self, config: &GuildConfig) -> u32 {
+        self.points.unwrap_or_else(|| config.points.weight(&self.punishment))
+    }
+
+    pub fn current_points(&self, config: &GuildConfig, now: Timestamp) -> u32 {
+        if self.expired.is_some() {
+            return 0;
+        }
+        let weight = self.weight(config);
+        let days = config.decay.days(&self.punishment);
+        let Some(due) = self.decay_due(days) else {
+            return weight;
+        };
+        let window = days as i64 * 86400;
+        let left = (due.unix_timestamp() - now.unix_timestamp()).clamp(0, window);
+        // Rounded up, so points only reach zero at the end of the window.
+        ((weight as i64 * left + window - 1) / window) as u32
+    }
+}
+
+pub fn point_total(punishments: &BTreeMap<String, PunishmentRecord>, config: &GuildConfig, now: Timestamp) -> u32 {
+    punishments.values().map(|record| record.current_points(config, now)).sum()
+}
+
+impl Profile {
+    // Returns whether the total changed, so the sweep only refreshes threads that show a new value.
+    pub fn refresh_points(&mut self, config: &GuildConfig) -> bool {
+        let total = point_total(&self.punishments, config, Timestamp::now());
+        let changed = total != self.points;
+        self.points = total;
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::fixtures::{DAY, at, record};
+    use crate::discord::commands::PunishmentType;
+
+    // Given on day 1000 and worth its config weight of 4, unless set otherwise.
+    fn warn(points: Option<u32>) -> PunishmentRecord {
+        let mut warn = record(PunishmentType::Warn, 1000 * DAY, None);
+        warn.points = points;
+        warn
+    }
+
+    fn decaying(days: u32) -> GuildConfig {
+        let mut config = GuildConfig::default();
+        config.decay.warn = days;
+        config.points.warn = 4;
+        config
+    }
+
+    #[test]
+    fn records_keep_the_weight_they_were_given() {
+        let config = decaying(0);
+        assert_eq!(warn(Some(7)).weight(&config), 7);
+        // Records from before points fall back to the current weight.
+        assert_eq!(warn(None).weight(&config), 4);
+    }
+
+    #[test]
+    fn full_weight_without_a_window() {
+        assert_eq!(warn(None).current_points(&decaying(0), at(5000 * DAY)), 4);
+    }
+
+    #[test]
+    fn fades_over_the_window_rounding_up() {
+        let config = decaying(10);
+        assert_eq!(warn(None).current_points(&config, at(1000 * DAY)), 4);
+        assert_eq!(warn(None).current_points(&config, at(1005 * DAY)), 2);
+        assert_eq!(warn(None).current_points(&config, at(1009 * DAY)), 1);
+        assert_eq!(warn(None).current_points(&config, at(1010 * DAY - 1)), 1);
+        assert_eq!(warn(None).current_points(&config, at(1010 * DAY)), 0);
+    }
+
+    #[test]
+    fn expired_records_are_worth_nothing() {
+        let mut record = warn(None);
+        record.expired = Some(at(1000 * DAY));
+        assert_eq!(record.current_points(&decaying(0), at(1000 * DAY)), 0);
+    }
+
+    #[test]
+    fn totals_every_record() {
+        let mut expired = warn(Some(3));
+        expired.expired = Some(at(1000 * DAY));
+        let punishments = BTreeMap::from([
+            ("1".to_string(), warn(None)),
+            ("2".to_string(), warn(Some(2))),
+            ("3".to_string(), expired),
+        ]);
+        assert_eq!(point_total(&punishments, &decaying(0), at(1000 * DAY)), 6);
+    }
+}
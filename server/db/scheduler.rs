@@ -0,0 +1,215 @@
+use super::{DBHandler, Temporary, decay::SWEEP_INTERVAL};
+use polodb_core::{CollectionT, bson::doc};
+use serde::{Deserialize, Serialize};
+use serenity::{
+    model::{Timestamp, id::{ChannelId, GuildId}},
+    utils::{FormattedTimestamp, FormattedTimestampStyle},
+};
+use tokio::time::Duration;
+
+// Longest the timer loop sleeps before re-reading the clock, so wall-clock changes or a suspend are caught within a minute.
+const MAX_WAIT: i64 = 60;
+
+/*
+Jobs are stored per guild in the "Jobs" collection and mirrored in DBHandler::jobs as (due, guild, job_id).
+The timer loop in process_requests only ever sleeps until the first entry of that set.
+*/
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Job {
+    pub job_id: String,
+    pub due: i64, //Unix time
+    pub kind: JobKind,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub enum JobKind {
+    Expire {
+        user_id: i64,
+        punishment_id: String,
+    },
+    Reminder {
+        channel: ChannelId,
+        invoker: i64,
+        message: String,
+    },
+    DecaySweep,
+}
+
+impl Job {
+    pub fn expire(temp: &Temporary) -> Self {
+        Job {
+            job_id: Job::expire_id(temp.user_id, &temp.punishment.id),
+            due: temp.punishment.punished_for.1.unix_timestamp(),
+            kind: JobKind::Expire {
+                user_id: temp.user_id,
+                punishment_id: temp.punishment.id.clone(),
+            },
+        }
+    }
+
+    // Expiry ids are derived from the record so edits and removals can find the job without a lookup.
+    pub fn expire_id(userid: i64, pid: &str) -> String {
+        format!("expire-{}-{}", userid, pid)
+    }
+
+    // One sweep per guild, rescheduled every time it runs.
+    pub fn decay_sweep(due: i64) -> Self {
+        Job {
+            job_id: "decay-sweep".to_string(),
+            due,
+            kind: JobKind::DecaySweep,
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        let due = match Timestamp::from_unix_timestamp(self.due) {
+            Ok(due) => FormattedTimestamp::new(due, Some(FormattedTimestampStyle::RelativeTime)).to_string(),
+            Err(_) => self.due.to_string(),
+        };
+        match &self.kind {
+            JobKind::Expire { user_id, punishment_id } => {
+                format!("`{}` - Expire punishment {} for <@{}> {}", self.job_id, punishment_id, user_id, due)
+            }
+            JobKind::Reminder { channel, invoker, .. } => {
+                format!("`{}` - Reminder from <@{}> in <#{}> {}", self.job_id, invoker, channel, due)
+            }
+            JobKind::DecaySweep => {
+                format!("`{}` - Expire decayed punishments {}", self.job_id, due)
+            }
+        }
+    }
+}
+
+impl DBHandler {
+    pub(super) fn next_wait(&self) -> Duration {
+        // Without a context nothing can run, so avoid spinning on overdue jobs.
+        if self.context.is_none() {
+            return Duration::from_secs(MAX_WAIT as u64);
+        }
+        let wait = match self.jobs.first() {
+            Some((due, _, _)) => (*due - Timestamp::now().unix_timestamp()).clamp(0, MAX_WAIT),
+            None => MAX_WAIT,
+        };
+        Duration::from_secs(wait as u64)
+    }
+
+    pub(super) fn schedule_job(&mut self, guildid: GuildId, job: Job) {
+        // Scheduling an existing id replaces it, which is how edits reschedule.
+        self.unschedule_job(guildid, &job.job_id);
+        if let Some(guilddb) = self.database.get(&guildid) {
+            if let Err(e) = guilddb.jobcol.insert_one(&job) {
+                eprintln!("Error creating new job in Job Schedule: {}", e);
+                return;
+            }
+        } else {
+            eprintln!("No database found for queried guild in Job Schedule");
+            return;
+        }
+        self.jobs.insert((job.due, guildid, job.job_id));
+    }
+
+    pub(super) fn get_job(&self, guildid: GuildId, job_id: &str) -> Option<Job> {
+        match self.database.get(&guildid) {
+            Some(guilddb) => match guilddb.jobcol.find_one(doc! { "job_id": job_id }) {
+                Ok(job) => job,
+                Err(e) => {
+                    eprintln!("Error retrieving job in Job Cancel: {}", e);
+                    None
+                }
+            },
+            None => None,
+        }
+    }
+
+    pub(super) fn cancel_job(&mut self, guildid: GuildId, job_id: &str) -> Option<Job> {
+        let job = self.get_job(guildid, job_id);
+        self.unschedule_job(guildid, job_id);
+        job
+    }
+
+    fn unschedule_job(&mut self, guildid: GuildId, job_id: &str) {
+        self.jobs.retain(|(_, guild, id)| !(*guild == guildid && id == job_id));
+        if let Some(guilddb) = self.database.get(&guildid)
+            && let Err(e) = guilddb.jobcol.delete_one(doc! { "job_id": job_id })
+        {
+            eprintln!("Error removing job in Job Cancel: {}", e);
+        }
+    }
+
+    pub(super) fn list_jobs(&self, guildid: &GuildId) -> Vec<Job> {
+        if let Some(guilddb) = self.database.get(guildid) {
+            match guilddb.jobcol.find(doc! {}).sort(doc! { "due": 1 }).run() {
+                Ok(cursor) => cursor.filter_map(|job| job.ok()).collect(),
+                Err(e) => {
+                    eprintln!("Error retrieving jobs in Job List: {}", e);
+                    Vec::new()
+                }
+            }
+        } else {
+            eprintln!("No database found for queried guild in Job List");
+            Vec::new()
+        }
+    }
+
+    pub(super) fn restore_jobs(&mut self, guildid: GuildId) {
+        for job in self.list_jobs(&guildid) {
+            self.jobs.insert((job.due, guildid, job.job_id));
+        }
+
+        // A cancelled sweep comes back on the next start.
+        let sweep = Job::decay_sweep(Timestamp::now().unix_timestamp());
+        if !self.jobs.iter().any(|(_, guild, id)| *guild == guildid && *id == sweep.job_id) {
+            self.schedule_job(guildid, sweep);
+        }
+
+        // Temporaries written before the scheduler existed have no job yet.
+        let temps = match self.database.get(&guildid) {
+            Some(guilddb) => match guilddb.tempcol.find(doc! {}).run() {
+                Ok(cursor) => cursor.filter_map(|temp| temp.ok()).collect::<Vec<Temporary>>(),
+                Err(e) => {
+                    eprintln!("Error retrieving temporaries in Job Restore: {}", e);
+                    return;
+                }
+            },
+            None => return,
+        };
+        for temp in temps {
+            let job = Job::expire(&temp);
+            if !self.jobs.iter().any(|(_, guild, id)| *guild == guildid && *id == job.job_id) {
+                println!("Scheduling expiry of punishment {} for user {} in guild {}.", temp.punishment.id, temp.user_id, guildid);
+                self.schedule_job(guildid, job);
+            }
+        }
+    }
+
+    pub(super) async fn run_due_jobs(&mut self) {
+        let Some(ctx) = self.context.clone() else {
+            return;
+        };
+        let now = Timestamp::now().unix_timestamp();
+        while let Some((due, guildid, job_id)) = self.jobs.first().cloned() {
+            if due > now {
+                break;
+            }
+            let Some(job) = self.cancel_job(guildid, &job_id) else {
+                continue;
+            };
+            match job.kind {
+                JobKind::Expire { user_id, punishment_id } => {
+                    self.expire_temporary(user_id, &guildid, punishment_id, &ctx).await;
+                    println!("Temporary punishment for user {} has completed.", user_id);
+                }
+                JobKind::Reminder { channel, invoker, message } => {
+                    if let Err(e) = channel.say(&ctx.http, format!("<@{}> Reminder: {}", invoker, message)).await {
+                        eprintln!("Error sending reminder in Job Run: {}", e);
+                    }
+                }
+                JobKind::DecaySweep => {
+                    self.sweep_decay(guildid, &ctx).await;
+                    self.schedule_job(guildid, Job::decay_sweep(now + SWEEP_INTERVAL));
+                }
+            }
+        }
+    }
+}
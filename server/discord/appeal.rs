@@ -0,0 +1,125 @@
+use serenity::{
+    all::{ActionRowComponent, ButtonStyle, CreateActionRow, CreateButton, CreateInputText, CreateModal, InputTextStyle, ModalInteraction},
+    model::id::GuildId,
+};
+
+/*
+Appeal buttons and modals are posted in DMs and threads, so everything needed to act on them
+is kept in the custom ID: appeal:<action>:<guild>:<user>:<punishment id>
+*/
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AppealAction {
+    Open,          //User pressed Appeal on a notice
+    Submit,        //User sent the appeal modal
+    Approve,
+    Deny,
+    Commute,       //Moderator pressed Commute, opens the duration modal
+    CommuteSubmit,
+}
+
+impl AppealAction {
+    fn name(&self) -> &'static str {
+        match self {
+            AppealAction::Open => "open",
+            AppealAction::Submit => "submit",
+            AppealAction::Approve => "approve",
+            AppealAction::Deny => "deny",
+            AppealAction::Commute => "commute",
+            AppealAction::CommuteSubmit => "commutesubmit",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "open" => Some(AppealAction::Open),
+            "submit" => Some(AppealAction::Submit),
+            "approve" => Some(AppealAction::Approve),
+            "deny" => Some(AppealAction::Deny),
+            "commute" => Some(AppealAction::Commute),
+            "commutesubmit" => Some(AppealAction::CommuteSubmit),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AppealId {
+    pub action: AppealAction,
+    pub guild: GuildId,
+    pub user: i64,
+    pub pid: String,
+}
+
+impl AppealId {
+    pub fn new(action: AppealAction, guild: GuildId, user: i64, pid: &str) -> Self {
+        AppealId { action, guild, user, pid: pid.to_string() }
+    }
+
+    pub fn with(&self, action: AppealAction) -> Self {
+        AppealId { action, ..self.clone() }
+    }
+
+    pub fn custom_id(&self) -> String {
+        format!("appeal:{}:{}:{}:{}", self.action.name(), self.guild, self.user, self.pid)
+    }
+
+    pub fn parse(custom_id: &str) -> Option<Self> {
+        let mut parts = custom_id.strip_prefix("appeal:")?.splitn(4, ':');
+        Some(AppealId {
+            action: AppealAction::from_name(parts.next()?)?,
+            guild: GuildId::new(parts.next()?.parse().ok()?),
+            user: parts.next()?.parse().ok()?,
+            pid: parts.next()?.to_string(),
+        })
+    }
+}
+
+pub fn appeal_button(guild: GuildId, user: i64, pid: &str) -> CreateActionRow {
+    CreateActionRow::Buttons(vec![
+        CreateButton::new(AppealId::new(AppealAction::Open, guild, user, pid).custom_id())
+            .label("Appeal")
+            .style(ButtonStyle::Primary),
+    ])
+}
+
+pub fn decision_buttons(guild: GuildId, user: i64, pid: &str) -> CreateActionRow {
+    CreateActionRow::Buttons(vec![
+        CreateButton::new(AppealId::new(AppealAction::Approve, guild, user, pid).custom_id())
+            .label("Approve")
+            .style(ButtonStyle::Success),
+        CreateButton::new(AppealId::new(AppealAction::Commute, guild, user, pid).custom_id())
+            .label("Commute")
+            .style(ButtonStyle::Secondary),
+        CreateButton::new(AppealId::new(AppealAction::Deny, guild, user, pid).custom_id())
+            .label("Deny")
+            .style(ButtonStyle::Danger),
+    ])
+}
+
+pub fn appeal_modal(id: &AppealId) -> CreateModal {
+    CreateModal::new(id.with(AppealAction::Submit).custom_id(), format!("Appeal punishment {}", id.pid))
+        .components(vec![CreateActionRow::InputText(
+            CreateInputText::new(InputTextStyle::Paragraph, "Why should this punishment be lifted?", "statement")
+                .max_length(1000)
+                .required(true),
+        )])
+}
+
+pub fn commute_modal(id: &AppealId) -> CreateModal {
+    CreateModal::new(id.with(AppealAction::CommuteSubmit).custom_id(), format!("Commute punishment {}", id.pid))
+        .components(vec![CreateActionRow::InputText(
+            CreateInputText::new(InputTextStyle::Short, "New total duration (i.e. 1d, 12h, perm)", "duration")
+                .required(true),
+        )])
+}
+
+pub fn modal_value(modal: &ModalInteraction, field: &str) -> Option<String> {
+    modal.data.components
+        .iter()
+        .flat_map(|row| row.components.iter())
+        .find_map(|component| match component {
+            ActionRowComponent::InputText(input) if input.custom_id == field => input.value.clone(),
+            _ => None,
+        })
+}
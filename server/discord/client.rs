@@ -1,39 +1,105 @@
 use crate::{
-    db::*,
+    db::{*, MessageSnapshot},
     discord::commands::ModbotCmd,
-    discord::commands::{CommandOptions, PunishmentAction, PunishmentType},
+    discord::commands::{Capability, CommandOptions, ConfigAction, EscalationAction, PunishmentAction, PunishmentType, ScheduleAction, PROFILE_MENU, PUNISH_AUTHOR_MENU, PUNISH_MENU, TIMEOUT_MENU, WARN_MENU},
+    discord::menu::{parse_punish_modal_id, punish_modal},
+    discord::duration::{Length, MAX_TIMEOUT, format_duration, parse_duration},
+    discord::permission::{authorized, required_capability},
+    discord::appeal::{AppealAction, AppealId, appeal_modal, commute_modal, modal_value},
+    discord::profile::{ProfileAction, ProfileId, edit_reason_modal, extend_modal, note_modal, parse_page_id},
+    discord::purge::{PurgeScope, purge_messages},
+    discord::responder::Responder,
+    discord::thread::note_thread,
 };
 
 
 use serenity::{
     all::{
-        CreateEmbed, CreateForumPost,CreateMessage, EditThread, ResolvedValue
+        audit_log::{Action, Change, MemberAction}, automod::{Action as AutoModAction, ActionExecution}, AuditLogEntry, GuildMemberFlags, GuildMemberUpdateEvent,
+        CommandInteraction, ComponentInteraction, CreateEmbed, CreateForumPost,CreateMessage, EditThread, ModalInteraction, ResolvedOption, ResolvedTarget, ResolvedValue, User
     },
     async_trait,
-    builder::{CreateChannel, CreateInteractionResponse, CreateInteractionResponseMessage, EditRole},
-    model::{application::Interaction, channel::*, id::GuildId, permissions::Permissions},
+    builder::{CreateChannel, CreateInteractionResponse, EditChannel, CreateInteractionResponseMessage, EditInteractionResponse, EditRole},
+    model::{application::Interaction, channel::*, guild::{Member, PartialMember}, id::{ChannelId, GuildId, MessageId, RoleId, UserId}, permissions::Permissions, Timestamp},
     prelude::*,
+    utils::parse_message_url,
 };
-use tokio::sync::mpsc::Sender;
+use std::collections::BTreeMap;
+use tokio::{sync::{mpsc::Sender, oneshot}, time::{sleep, Duration}};
+
+// Default names, only used until the guild's GuildConfig points at its own channels and role.
+const LOG_CHANNEL: &str = "modbot-log";
+const NOTIFIER_CHANNEL: &str = "punishment-notifications";
+// Older versions made the notifier a forum, it keeps its posts under this name.
+const LEGACY_NOTIFIER_CHANNEL: &str = "punishment-notifications-archive";
+const MUTE_ROLE: &str = "Muted";
+
+// How long a "Punish author" snapshot waits for its modal to be submitted.
+const PENDING_EVIDENCE_TTL: i64 = 15 * 60;
+
+// Audit log entries can trail the gateway event, so the lookup is retried a few times.
+const AUDIT_ATTEMPTS: u32 = 3;
+const AUDIT_RETRY: Duration = Duration::from_secs(2);
+// Only entries this recent can belong to the event.
+const AUDIT_WINDOW: i64 = 60;
+
+// The "Block member interactions" AutoMod action, which serenity doesn't name yet. It quarantines the member.
+const BLOCK_MEMBER_INTERACTION: u8 = 4;
+// How long an AutoMod quarantine waits for the member update that confirms it.
+const PENDING_QUARANTINE_TTL: i64 = 60;
+const QUARANTINE_FLAGS: GuildMemberFlags = GuildMemberFlags::AUTOMOD_QUARANTINED_USERNAME.union(GuildMemberFlags::AUTOMOD_QUARANTINED_GUILD_TAG);
 
 pub struct ClientHandler {
     sender: Sender<DBRequest>,
+    punished: PunishedSet,
+    own_actions: OwnActions,
+    roles: RoleCache,
+    // Messages snapshotted when "Punish author" opened its modal, claimed on submit by the same moderator.
+    pending_evidence: RwLock<BTreeMap<(MessageId, UserId), (Timestamp, Evidence)>>,
+    // Members AutoMod just blocked, confirmed as quarantined by the member update that follows.
+    pending_quarantine: RwLock<BTreeMap<(GuildId, UserId), Timestamp>>,
 }
 
-use regex::Regex;
-
 
 impl ClientHandler {
-    pub fn new(sender: Sender<DBRequest>) -> Self {
-        ClientHandler { sender }
+    pub fn new(sender: Sender<DBRequest>, punished: PunishedSet, own_actions: OwnActions, roles: RoleCache) -> Self {
+        ClientHandler {
+            sender,
+            punished,
+            own_actions,
+            roles,
+            pending_evidence: RwLock::new(BTreeMap::new()),
+            pending_quarantine: RwLock::new(BTreeMap::new()),
+        }
+    }
+
+    async fn fetch_config(&self, guild: GuildId) -> GuildConfig {
+        let (reply, config) = oneshot::channel();
+        if let Err(e) = self
+            .sender
+            .send(DBRequest {
+                request_type: DBRequestType::Config,
+                command: Some(Command::GetConfig { targetguild: guild, reply }),
+                context: None,
+                threadlog: None,
+            })
+            .await
+        {
+            eprintln!("Error sending Config event {}", e);
+        }
+        config.await.unwrap_or_default()
     }
 
-    async fn create_log(ctx: &Context, guild: GuildId) -> Result<GuildChannel, SerenityError> {
+    // The configured channel is used when it still exists, the default name only finds or seeds one.
+    async fn create_log(ctx: &Context, guild: GuildId, configured: Option<ChannelId>) -> Result<GuildChannel, SerenityError> {
         let gch = guild.channels(&ctx.http).await?;
-        if let Some(channel) = gch.values().find(|channel| channel.name == "modbot-log") {
+        if let Some(channel) = configured
+            .and_then(|id| gch.get(&id))
+            .or_else(|| gch.values().find(|channel| channel.name == LOG_CHANNEL))
+        {
             Ok(channel.clone())
         } else {
-            let logbuilder = CreateChannel::new("modbot-log")
+            let logbuilder = CreateChannel::new(LOG_CHANNEL)
                 .kind(ChannelType::Forum)
                 .topic("Moderation Profiles of Users")
                 .permissions(vec![PermissionOverwrite {
@@ -63,29 +129,56 @@ impl ClientHandler {
         }
     }
 
-    async fn create_punishment_notifer(ctx: &Context, guild: GuildId) -> Result<GuildChannel, SerenityError> {
+    // Read only text channel, each punished user gets a private thread in it. Forum posts can't be hidden per user.
+    async fn create_punishment_notifer(ctx: &Context, guild: GuildId, configured: Option<ChannelId>, mute_role: Option<RoleId>) -> Result<GuildChannel, SerenityError> {
         let gch = guild.channels(&ctx.http).await?;
-        if let Some(channel) = gch.values().find(|channel| channel.name == "punishment-notifications") {
-            Ok(channel.clone())
+        let readonly = Permissions::VIEW_CHANNEL | Permissions::READ_MESSAGE_HISTORY;
+        let named = gch.values().find(|channel| channel.name == NOTIFIER_CHANNEL);
+        let notifier = if let Some(channel) = configured
+            .and_then(|id| gch.get(&id))
+            .filter(|channel| channel.kind == ChannelType::Text)
+            .or_else(|| named.filter(|channel| channel.kind == ChannelType::Text))
+        {
+            channel.clone()
         } else {
-            let notifybuilder = CreateChannel::new("punishment-notifications")
-                .kind(ChannelType::Forum)
+            // Discord can't turn a forum into a text channel, so the old one is renamed and the new channel takes its name.
+            if let Some(old) = named {
+                let mut old = old.clone();
+                old.edit(&ctx.http, EditChannel::new().name(LEGACY_NOTIFIER_CHANNEL)).await?;
+                println!("Renamed old punishment-notifications channel {} to {}", old.id, LEGACY_NOTIFIER_CHANNEL);
+            }
+            let notifybuilder = CreateChannel::new(NOTIFIER_CHANNEL)
+                .kind(ChannelType::Text)
                 .topic("Active punishment notifications for users")
                 .permissions(vec![PermissionOverwrite {
-                    allow: Permissions::empty(),
-                    deny: Permissions::VIEW_CHANNEL
-                        | Permissions::SEND_MESSAGES
-                        | Permissions::READ_MESSAGE_HISTORY,
+                    allow: readonly,
+                    deny: Permissions::SEND_MESSAGES
+                        | Permissions::SEND_MESSAGES_IN_THREADS
+                        | Permissions::CREATE_PUBLIC_THREADS
+                        | Permissions::CREATE_PRIVATE_THREADS,
                     kind: PermissionOverwriteType::Role(guild.everyone_role()),
                 }]);
             let channel = guild.create_channel(&ctx.http, notifybuilder).await?;
             println!("Created mod-notifications channel: {}", channel.id);
-            Ok(channel.clone())
+            channel
+        };
+        // Muted users are denied every other channel, they still have to be able to read their notices.
+        if let Some(role) = mute_role {
+            notifier.create_permission(&ctx.http, PermissionOverwrite {
+                allow: readonly,
+                deny: Permissions::all() - readonly,
+                kind: PermissionOverwriteType::Role(role),
+            }).await?;
         }
+        Ok(notifier)
     }
 
-    async fn role_add(ctx: &Context, guild: GuildId, channels: &Vec<&GuildChannel>, deny: Permissions, rolename: &str) -> Result<(), SerenityError> {
-        if let Some(role) = guild.roles(&ctx.http).await?.values().find(|role| role.name == rolename) {
+    async fn role_add(ctx: &Context, guild: GuildId, channels: &Vec<&GuildChannel>, deny: Permissions, configured: Option<RoleId>) -> Result<RoleId, SerenityError> {
+        let roles = guild.roles(&ctx.http).await?;
+        if let Some(role) = configured
+            .and_then(|id| roles.get(&id))
+            .or_else(|| roles.values().find(|role| role.name == MUTE_ROLE))
+        {
             for channel in channels {
                 let _ = channel.create_permission(&ctx.http, PermissionOverwrite {
                     allow: Permissions::empty(),
@@ -93,9 +186,10 @@ impl ClientHandler {
                     kind: PermissionOverwriteType::Role(role.id),
                 }).await;
             }
+            Ok(role.id)
         } else {
             let newrole = guild.create_role(&ctx.http, EditRole::new()
-                .name(rolename)
+                .name(MUTE_ROLE)
                 .mentionable(false)
             ).await?;
             for channel in channels {
@@ -105,8 +199,8 @@ impl ClientHandler {
                     kind: PermissionOverwriteType::Role(newrole.id),
                 }).await?;
             }
+            Ok(newrole.id)
         }
-        Ok(())
     } 
 
     async fn permission_check(ctx: &Context, guild: GuildId) -> Result<bool, SerenityError> {
@@ -122,33 +216,558 @@ impl ClientHandler {
         }
     }
 
-    fn millis(duration: String) -> Option<i64> {
-        match Regex::new(r"(?i)^(\d+)([MHD])$") {
-            Ok(re) => {
-                if let Some(caps) = re.captures(&duration) {
-                    let number = caps.get(1)?.as_str().parse::<i64>();
-                    let unit = caps.get(2)?.as_str();
-                    match number {
-                        Ok(num) => {
-                            match unit {
-                                "M" => Some(num * 60),
-                                "H" => Some(num * 60 * 60),
-                                "D" => Some(num * 60 * 60 * 24),
-                                "m" => Some(num * 60),
-                                "h" => Some(num * 60 * 60),
-                                "d" => Some(num * 60 * 60 * 24),
-                                _ => None,
-                            }
+    async fn component_interaction(&self, ctx: Context, component: ComponentInteraction) {
+        if let Some(id) = ProfileId::parse(&component.data.custom_id) {
+            return self.profile_component(ctx, component, id).await;
+        }
+        if let Some((guild, user, page)) = parse_page_id(&component.data.custom_id) {
+            return self.page_component(ctx, component, guild, user, page).await;
+        }
+        let Some(id) = AppealId::parse(&component.data.custom_id) else {
+            return;
+        };
+        let response = match id.action {
+            AppealAction::Open if component.user.id.get() as i64 != id.user => {
+                ClientHandler::ephemeral("Only the punished user can appeal this punishment.")
+            }
+            AppealAction::Open => CreateInteractionResponse::Modal(appeal_modal(&id)),
+            AppealAction::Approve | AppealAction::Deny | AppealAction::Commute => {
+                let capability = if id.action == AppealAction::Approve { Capability::Remove } else { Capability::Edit };
+                if !self.member_allowed(id.guild, component.member.as_ref(), capability).await {
+                    ClientHandler::ephemeral(&format!("You lack the {:?} permission required to decide appeals.", capability))
+                } else if id.action == AppealAction::Commute {
+                    CreateInteractionResponse::Modal(commute_modal(&id))
+                } else {
+                    let decision = if id.action == AppealAction::Approve { AppealState::Approved } else { AppealState::Denied };
+                    return self.send_appeal_decision(ctx, component.into(), id, decision, None).await;
+                }
+            }
+            _ => return,
+        };
+        if let Err(e) = component.create_response(&ctx.http, response).await {
+            eprintln!("Error responding to appeal button {}", e);
+        }
+    }
+
+    async fn modal_interaction(&self, ctx: Context, modal: ModalInteraction) {
+        if let Some((guild, user, message)) = parse_punish_modal_id(&modal.data.custom_id) {
+            return self.punish_modal(ctx, modal, guild, user, message).await;
+        }
+        if let Some(id) = ProfileId::parse(&modal.data.custom_id) {
+            return self.profile_modal(ctx, modal, id).await;
+        }
+        let Some(id) = AppealId::parse(&modal.data.custom_id) else {
+            return;
+        };
+        match id.action {
+            AppealAction::Submit => {
+                let statement = modal_value(&modal, "statement").unwrap_or_default();
+                self.sender
+                    .send(DBRequest {
+                        request_type: DBRequestType::Appeal,
+                        command: Some(Command::AppealSubmit {
+                            targetguild: id.guild,
+                            user: modal.user.clone(),
+                            pid: id.pid,
+                            statement,
+                            command: modal.into(),
+                        }),
+                        context: Some(ctx),
+                        threadlog: None,
+                    })
+                    .await
+                    .unwrap_or_else(|e| {
+                        eprintln!("Error sending Appeal event {}", e);
+                    });
+            }
+            AppealAction::CommuteSubmit => {
+                // Checked again as the modal could be submitted after permissions changed.
+                let error = if !self.member_allowed(id.guild, modal.member.as_ref(), Capability::Edit).await {
+                    format!("You lack the {:?} permission required to decide appeals.", Capability::Edit)
+                } else {
+                    match parse_duration(&modal_value(&modal, "duration").unwrap_or_default()) {
+                        Ok(length) => {
+                            return self.send_appeal_decision(ctx, modal.into(), id, AppealState::Commuted, Some(length)).await;
                         }
-                        Err(_) => None,
+                        Err(e) => e,
                     }
-                  
-                    } else {
-                        None
+                };
+                if let Err(e) = modal.create_response(&ctx.http, ClientHandler::ephemeral(&error)).await {
+                    eprintln!("Error responding to commute modal {}", e);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Same capabilities as the matching /punish subcommands.
+    fn profile_capability(action: ProfileAction) -> Capability {
+        match action {
+            ProfileAction::RemoveLatest => Capability::Remove,
+            _ => Capability::Edit,
+        }
+    }
+
+    async fn profile_component(&self, ctx: Context, component: ComponentInteraction, id: ProfileId) {
+        let capability = ClientHandler::profile_capability(id.action);
+        let response = if !self.member_allowed(id.guild, component.member.as_ref(), capability).await {
+            ClientHandler::ephemeral(&format!("You lack the {:?} permission required for this action.", capability))
+        } else {
+            match id.action {
+                // The latest record may have ended long ago, PunishRemove then drops it without lifting anything.
+                ProfileAction::RemoveLatest => {
+                    let Some(target) = ClientHandler::fetch_target(&ctx, id.guild, id.user).await else {
+                        return;
+                    };
+                    let invoker = component.user.clone();
+                    self.sender
+                        .send(DBRequest {
+                            request_type: DBRequestType::Punishment,
+                            command: Some(Command::PunishRemove {
+                                command: component.into(),
+                                targetguild: id.guild,
+                                target,
+                                invoker,
+                                latest: Some(true),
+                                id: None,
+                                silent: false,
+                                notify: None,
+                            }),
+                            context: Some(ctx),
+                            threadlog: None,
+                        })
+                        .await
+                        .unwrap_or_else(|e| {
+                            eprintln!("Error sending Punishment event {}", e);
+                        });
+                    return;
+                }
+                ProfileAction::EditReason => CreateInteractionResponse::Modal(edit_reason_modal(&id)),
+                ProfileAction::Extend => CreateInteractionResponse::Modal(extend_modal(&id)),
+                ProfileAction::AddNote => CreateInteractionResponse::Modal(note_modal(&id)),
+                _ => return,
+            }
+        };
+        if let Err(e) = component.create_response(&ctx.http, response).await {
+            eprintln!("Error responding to profile button {}", e);
+        }
+    }
+
+    async fn profile_modal(&self, ctx: Context, modal: ModalInteraction, id: ProfileId) {
+        let capability = ClientHandler::profile_capability(id.action);
+        if !self.member_allowed(id.guild, modal.member.as_ref(), capability).await {
+            let content = format!("You lack the {:?} permission required for this action.", capability);
+            if let Err(e) = modal.create_response(&ctx.http, ClientHandler::ephemeral(&content)).await {
+                eprintln!("Error responding to profile modal {}", e);
+            }
+            return;
+        }
+
+        // An empty ID falls back to the latest punishment.
+        let pid = modal_value(&modal, "id").filter(|pid| !pid.trim().is_empty()).map(|pid| pid.trim().to_string());
+        let latest = pid.is_none().then_some(true);
+        let (reason, length) = match id.action {
+            ProfileAction::EditReasonSubmit => (modal_value(&modal, "reason"), None),
+            ProfileAction::ExtendSubmit => match parse_duration(&modal_value(&modal, "duration").unwrap_or_default()) {
+                Ok(Length::Timed(seconds)) => (None, Some(Length::Extend(seconds))),
+                Ok(length) => (None, Some(length)),
+                Err(e) => {
+                    if let Err(e) = modal.create_response(&ctx.http, ClientHandler::ephemeral(&e)).await {
+                        eprintln!("Error responding to profile modal {}", e);
                     }
+                    return;
+                }
+            },
+            ProfileAction::AddNoteSubmit => {
+                // The modal was opened from the profile post, so its channel is the profile thread.
+                let note = format!("📝 Note from <@{}>: {}", modal.user.id, modal_value(&modal, "note").unwrap_or_default());
+                let content = match note_thread(&ctx, &modal.channel_id, note).await {
+                    Ok(_) => "Note added.".to_string(),
+                    Err(e) => format!("Failed to add note: {}", e),
+                };
+                if let Err(e) = modal.create_response(&ctx.http, ClientHandler::ephemeral(&content)).await {
+                    eprintln!("Error responding to profile modal {}", e);
                 }
-            Err(_) => None,
+                return;
+            }
+            _ => return,
+        };
+
+        let Some(target) = ClientHandler::fetch_target(&ctx, id.guild, id.user).await else {
+            return;
+        };
+        let invoker = modal.user.clone();
+        self.sender
+            .send(DBRequest {
+                request_type: DBRequestType::Punishment,
+                command: Some(Command::PunishEdit {
+                    command: modal.into(),
+                    targetguild: id.guild,
+                    target,
+                    invoker,
+                    reason,
+                    length,
+                    latest,
+                    id: pid,
+                    notify: None,
+                    evidence: Vec::new(),
+                }),
+                context: Some(ctx),
+                threadlog: None,
+            })
+            .await
+            .unwrap_or_else(|e| {
+                eprintln!("Error sending Punishment event {}", e);
+            });
+    }
+
+    async fn punish_modal(&self, ctx: Context, modal: ModalInteraction, guild: GuildId, user: i64, message: Option<MessageId>) {
+        let ptype = modal_value(&modal, "type").and_then(|ptype| PunishmentType::from_name(&ptype));
+        let length = match modal_value(&modal, "duration").filter(|d| !d.trim().is_empty()).as_deref().map(parse_duration) {
+            Some(Ok(length)) => Ok(Some(length)),
+            Some(Err(e)) => Err(e),
+            None => Ok(None),
+        };
+        let error = match (ptype, length) {
+            (None, _) => "Unknown punishment type, use warn, mute, timeout, kick, softban or ban.".to_string(),
+            (_, Err(e)) => e,
+            // Warnings never end, and kicks and softbans are over once they happened.
+            (Some(ptype), Ok(Some(_))) if ptype == PunishmentType::Warn || ptype.is_instant() => {
+                format!("{:?} punishments don't take a duration, leave it empty.", ptype)
+            }
+            (Some(ptype), _) if !self.member_allowed(guild, modal.member.as_ref(), Capability::for_punishment(&ptype)).await => {
+                format!("You lack the {:?} permission required for this command.", Capability::for_punishment(&ptype))
+            }
+            (Some(PunishmentType::Timeout), Ok(length)) if length.and_then(|l| l.seconds_from_now()).is_none_or(|s| s > MAX_TIMEOUT) => {
+                "Timeouts must end within 28 days.".to_string()
+            }
+            (Some(ptype), Ok(length)) => {
+                let delete_days = ptype.default_delete_days();
+                let Some(target) = ClientHandler::fetch_target(&ctx, guild, user).await else {
+                    return;
+                };
+                let invoker = modal.user.clone();
+                let reason = modal_value(&modal, "reason").filter(|r| !r.trim().is_empty());
+                let evidence = match message {
+                    Some(message) => match self.pending_evidence.write().await.remove(&(message, modal.user.id)) {
+                        Some((_, evidence)) => vec![evidence],
+                        None => {
+                            if let Err(e) = modal.create_response(&ctx.http, ClientHandler::ephemeral("The message snapshot expired, please use Punish author again.")).await {
+                                eprintln!("Error responding to punish modal {}", e);
+                            }
+                            return;
+                        }
+                    },
+                    None => Vec::new(),
+                };
+                self.sender
+                    .send(DBRequest {
+                        request_type: DBRequestType::Punishment,
+                        command: Some(Command::PunishAdd {
+                            command: modal.into(),
+                            targetguild: guild,
+                            target,
+                            invoker,
+                            ptype,
+                            reason,
+                            length,
+                            notify: None,
+                            evidence,
+                            delete_days,
+                            recorded: None,
+                        }),
+                        context: Some(ctx),
+                        threadlog: None,
+                    })
+                    .await
+                    .unwrap_or_else(|e| {
+                        eprintln!("Error sending Punishment event {}", e);
+                    });
+                return;
+            }
+        };
+        if let Err(e) = modal.create_response(&ctx.http, ClientHandler::ephemeral(&error)).await {
+            eprintln!("Error responding to punish modal {}", e);
+        }
+    }
+
+    async fn fetch_target(ctx: &Context, guild: GuildId, user: i64) -> Option<(User, Option<PartialMember>)> {
+        let user = match UserId::new(user as u64).to_user(ctx).await {
+            Ok(user) => user,
+            Err(e) => {
+                eprintln!("Error fetching target user {}", e);
+                return None;
+            }
+        };
+        let member = guild.member(&ctx.http, user.id).await.ok().map(PartialMember::from);
+        Some((user, member))
+    }
+
+    async fn page_component(&self, ctx: Context, component: ComponentInteraction, guild: GuildId, user: i64, page: usize) {
+        if !self.member_allowed(guild, component.member.as_ref(), Capability::Fetch).await {
+            if let Err(e) = component.create_response(&ctx.http, ClientHandler::ephemeral("You lack the Fetch permission required to view profiles.")).await {
+                eprintln!("Error responding to page button {}", e);
+            }
+            return;
         }
+        let Some(target) = ClientHandler::fetch_target(&ctx, guild, user).await else {
+            return;
+        };
+        let invoker = component.user.clone();
+        self.sender
+            .send(DBRequest {
+                request_type: DBRequestType::FetchProfile,
+                command: Some(Command::FetchProfile {
+                    command: component.into(),
+                    targetguild: guild,
+                    target,
+                    invoker,
+                    page,
+                }),
+                context: Some(ctx),
+                threadlog: None,
+            })
+            .await
+            .unwrap_or_else(|e| {
+                eprintln!("Error sending Fetch Profile event {}", e);
+            });
+    }
+
+    async fn send_appeal_decision(&self, ctx: Context, command: Responder, id: AppealId, decision: AppealState, length: Option<Length>) {
+        let Some(target) = ClientHandler::fetch_target(&ctx, id.guild, id.user).await else {
+            return;
+        };
+        self.sender
+            .send(DBRequest {
+                request_type: DBRequestType::Appeal,
+                command: Some(Command::AppealDecide {
+                    command,
+                    id,
+                    target,
+                    decision,
+                    length,
+                }),
+                context: Some(ctx),
+                threadlog: None,
+            })
+            .await
+            .unwrap_or_else(|e| {
+                eprintln!("Error sending Appeal event {}", e);
+            });
+    }
+
+    async fn member_allowed(&self, guild: GuildId, member: Option<&Member>, capability: Capability) -> bool {
+        match member {
+            Some(member) => authorized(self.roles.read().await.get(&guild), member, Some(capability)),
+            None => false,
+        }
+    }
+
+    // Message links are snapshotted like "Punish author", falling back to the bare link once the message is gone.
+    async fn collect_evidence(ctx: &Context, guild: GuildId, attachment: Option<Attachment>, message_link: Option<String>) -> Result<Vec<Evidence>, &'static str> {
+        let mut evidence = vec![];
+        if let Some(attachment) = attachment {
+            evidence.push(Evidence::Attachment(AttachmentEvidence::from_attachment(&attachment)));
+        }
+        if let Some(link) = message_link {
+            let Some((linkguild, channel, message)) = parse_message_url(link.trim()) else {
+                return Err("The message link is not a valid Discord message link.");
+            };
+            if linkguild != guild {
+                return Err("The message link must point to a message in this server.");
+            }
+            match channel.message(&ctx.http, message).await {
+                Ok(message) => evidence.push(Evidence::Message(MessageSnapshot::from_message(&message, guild))),
+                Err(e) => {
+                    eprintln!("Error fetching linked evidence message {}", e);
+                    evidence.push(Evidence::Link(link.trim().to_string()));
+                }
+            }
+        }
+        Ok(evidence)
+    }
+
+    // Options shared by every /punish add subcommand, each only declares the ones that apply to its type.
+    fn parse_add_options(opts: &mut CommandOptions, options: &[ResolvedOption]) {
+        for option in options {
+            match (option.name, &option.value) {
+                ("user", ResolvedValue::User(u, m)) => {
+                    if let Some(m) = m {
+                        opts.member = Some((**m).clone());
+                    }
+                    opts.user = Some((**u).clone());
+                }
+                ("duration", ResolvedValue::String(d)) => {
+                    opts.duration = Some((*d).to_string());
+                }
+                ("delete_days", ResolvedValue::Integer(d)) => {
+                    opts.delete_days = Some(*d);
+                }
+                ("reason", ResolvedValue::String(r)) => {
+                    opts.reason = Some((*r).to_string());
+                }
+                ("notify", ResolvedValue::Boolean(n)) => {
+                    opts.notify = Some(*n);
+                }
+                ("evidence", ResolvedValue::Attachment(a)) => {
+                    opts.attachment = Some((*a).clone());
+                }
+                ("message_link", ResolvedValue::String(l)) => {
+                    opts.message_link = Some((*l).to_string());
+                }
+                ("purge", ResolvedValue::String(p)) => {
+                    opts.purge = Some((*p).to_string());
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // Purging reads every channel and takes a while, so it amends the response DB_Handler already sent.
+    async fn purge_after(&self, ctx: Context, command: &CommandInteraction, guild: GuildId, target: (User, Option<PartialMember>), id: String, scope: PurgeScope) {
+        let status = match purge_messages(&ctx, guild, target.0.id, scope).await {
+            Ok(deleted) => {
+                self.sender
+                    .send(DBRequest {
+                        request_type: DBRequestType::Punishment,
+                        command: Some(Command::PunishPurged {
+                            targetguild: guild,
+                            target,
+                            invoker: command.user.clone(),
+                            id,
+                            purged: deleted as u32,
+                        }),
+                        context: Some(ctx.clone()),
+                        threadlog: None,
+                    })
+                    .await
+                    .unwrap_or_else(|e| {
+                        eprintln!("Error sending Punishment event {}", e);
+                    });
+                if deleted == 1 { " Purged 1 message.".to_string() } else { format!(" Purged {} messages.", deleted) }
+            }
+            Err(e) => {
+                eprintln!("Error purging messages of user {} {}", target.0.id, e);
+                format!(" The purge failed: {}.", e)
+            }
+        };
+        let content = match command.get_response(&ctx.http).await {
+            Ok(response) => format!("{}{}", response.content, status),
+            Err(_) => status.trim_start().to_string(),
+        };
+        if let Err(e) = command.edit_response(&ctx.http, EditInteractionResponse::new().content(content)).await {
+            eprintln!("Error amending purge response {}", e);
+        }
+    }
+
+    async fn audit_entry(ctx: &Context, guild: GuildId, target: UserId, change: ExternalChange) -> Option<AuditLogEntry> {
+        let action = match change {
+            ExternalChange::Ban => MemberAction::BanAdd,
+            ExternalChange::Unban => MemberAction::BanRemove,
+            ExternalChange::Timeout(_) | ExternalChange::TimeoutRemoved => MemberAction::Update,
+        };
+        for attempt in 0..AUDIT_ATTEMPTS {
+            if attempt > 0 {
+                sleep(AUDIT_RETRY).await;
+            }
+            let entries = match guild.audit_logs(&ctx.http, Some(Action::Member(action)), None, None, Some(10)).await {
+                Ok(logs) => logs.entries,
+                Err(e) => {
+                    eprintln!("Error reading audit log in guild {}: {}", guild, e);
+                    return None;
+                }
+            };
+            let now = Timestamp::now().unix_timestamp();
+            let found = entries.into_iter().find(|entry| {
+                entry.target_id.is_some_and(|id| id.get() == target.get())
+                    && now - entry.id.created_at().unix_timestamp() <= AUDIT_WINDOW
+                    // Member updates also cover nicknames and the like.
+                    && (!matches!(action, MemberAction::Update) || entry.changes.iter().flatten().any(|change| matches!(change, Change::CommunicationDisabledUntil { .. })))
+            });
+            if found.is_some() {
+                return found;
+            }
+        }
+        None
+    }
+
+    // Bans and timeouts given through Discord, attributed from the audit log. The bot's own actions are already on file.
+    async fn send_external(&self, ctx: Context, guild: GuildId, target: (User, Option<PartialMember>), change: ExternalChange) {
+        let entry = ClientHandler::audit_entry(&ctx, guild, target.0.id, change).await;
+        if entry.as_ref().is_some_and(|entry| entry.user_id == ctx.cache.current_user().id) {
+            return;
+        }
+        // Without an entry, a matching action the bot just took is far likelier than a moderator acting at the same moment.
+        if entry.is_none() && self.own_action(guild, target.0.id, change).await {
+            return;
+        }
+        let (moderator, reason) = match entry {
+            Some(entry) => (entry.user_id.to_user(&ctx).await.ok(), entry.reason),
+            None => (None, None),
+        };
+        self.sender
+            .send(DBRequest {
+                request_type: DBRequestType::External,
+                command: Some(Command::External {
+                    targetguild: guild,
+                    target,
+                    change,
+                    moderator,
+                    reason,
+                }),
+                context: Some(ctx),
+                threadlog: None,
+            })
+            .await
+            .unwrap_or_else(|e| {
+                eprintln!("Error sending External event {}", e);
+            });
+    }
+
+    async fn own_action(&self, guild: GuildId, user: UserId, change: ExternalChange) -> bool {
+        let now = Timestamp::now().unix_timestamp();
+        self.own_actions
+            .read()
+            .await
+            .get(&(guild, user.get() as i64, change.punishment()))
+            .is_some_and(|at| now - at.unix_timestamp() <= OWN_ACTION_TTL)
+    }
+
+    async fn send_automod(&self, ctx: Context, command: Command) {
+        self.sender
+            .send(DBRequest {
+                request_type: DBRequestType::AutoMod,
+                command: Some(command),
+                context: Some(ctx),
+                threadlog: None,
+            })
+            .await
+            .unwrap_or_else(|e| {
+                eprintln!("Error sending AutoMod event {}", e);
+            });
+    }
+
+    // Quarantine is confirmed by the member flags, either right after AutoMod blocked the member or as a change from the cached member.
+    async fn quarantine_change(&self, guild: GuildId, user: UserId, old: Option<&Member>, flags: Option<GuildMemberFlags>) -> Option<bool> {
+        let now = Timestamp::now().unix_timestamp();
+        let quarantined = flags.is_some_and(|flags| flags.intersects(QUARANTINE_FLAGS));
+        let before = old.map(|old| old.flags.intersects(QUARANTINE_FLAGS));
+        let mut pending = self.pending_quarantine.write().await;
+        pending.retain(|_, at| now - at.unix_timestamp() <= PENDING_QUARANTINE_TTL);
+        let blocked = quarantined && pending.remove(&(guild, user)).is_some();
+        match before {
+            Some(before) if before != quarantined => Some(quarantined),
+            _ if blocked => Some(true),
+            _ => None,
+        }
+    }
+
+    fn ephemeral(content: &str) -> CreateInteractionResponse {
+        CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new()
+                .content(content)
+                .ephemeral(true),
+        )
     }
 }
 
@@ -170,11 +789,12 @@ impl EventHandler for ClientHandler {
         for guild in guilds {
             match ClientHandler::permission_check(&ctx, guild).await {
                 Ok(true) => {
+                    let mut config = self.fetch_config(guild).await;
                     match guild.channels(&ctx.http).await {
                         Ok(channels) => {
                             let guildchs = channels.values().collect::<Vec<_>>();
-                            match ClientHandler::role_add(&ctx, guild, &guildchs, Permissions::all(), "Muted").await {
-                                Ok(_) => (),
+                            match ClientHandler::role_add(&ctx, guild, &guildchs, Permissions::all(), config.mute_role).await {
+                                Ok(role) => config.mute_role = Some(role),
                                 Err(e) => {
                                     eprintln!("Error applying Muted role to Guild {}: {}", guild, e);
                                     continue;
@@ -188,7 +808,7 @@ impl EventHandler for ClientHandler {
                     };
                     println!("Bot has permissions in connected guild {}", guild);
 
-                    let logchannel = match ClientHandler::create_log(&ctx, guild).await {
+                    let logchannel = match ClientHandler::create_log(&ctx, guild, config.log_channel).await {
                         Ok(log) => {
                            log
                         }
@@ -198,8 +818,23 @@ impl EventHandler for ClientHandler {
                         }
                     };
 
-                    match ClientHandler::create_punishment_notifer(&ctx, guild).await {
+                    match ClientHandler::create_punishment_notifer(&ctx, guild, config.notifier_channel, config.mute_role).await {
                         Ok(notifier) => {
+                            config.log_channel = Some(logchannel.id);
+                            config.notifier_channel = Some(notifier.id);
+                            if let Err(e) = &self
+                                .sender
+                                .send(DBRequest {
+                                    request_type: DBRequestType::Config,
+                                    command: Some(Command::SaveConfig { targetguild: guild, config }),
+                                    context: None,
+                                    threadlog: None,
+                                })
+                                .await
+                            {
+                                eprintln!("Error sending Config event {}", e);
+                            }
+
                             if let Err(e) = &self
                                 .sender
                                 .send(DBRequest {
@@ -220,6 +855,15 @@ impl EventHandler for ClientHandler {
                                         ModbotCmd::Punishment.build(),
                                         ModbotCmd::FetchProfile.build(),
                                         ModbotCmd::RoleSet.build(),
+                                        ModbotCmd::Schedule.build(),
+                                        ModbotCmd::ModConfig.build(),
+                                        ModbotCmd::Evidence.build(),
+                                        ModbotCmd::Escalation.build(),
+                                        ModbotCmd::ProfileMenu.build(),
+                                        ModbotCmd::WarnMenu.build(),
+                                        ModbotCmd::TimeoutMenu.build(),
+                                        ModbotCmd::PunishMenu.build(),
+                                        ModbotCmd::PunishAuthorMenu.build(),
                                     ],
                                 )
                                 .await
@@ -250,9 +894,13 @@ impl EventHandler for ClientHandler {
     }
 
     async fn channel_create(&self, ctx: Context, channel: GuildChannel) {
-        let mut channels = Vec::new();
-        channels.push(&channel);
-        match ClientHandler::role_add(&ctx, channel.guild_id, &channels, Permissions::all(), "Muted").await {
+        let channels = vec![&channel];
+        let config = self.fetch_config(channel.guild_id).await;
+        // The notifier keeps its own read only overwrite for the mute role.
+        if channel.name == NOTIFIER_CHANNEL || config.notifier_channel == Some(channel.id) {
+            return;
+        }
+        match ClientHandler::role_add(&ctx, channel.guild_id, &channels, Permissions::all(), config.mute_role).await {
             Ok(_) => (),
             Err(e) => {
                 eprintln!("Error applying Muted role to Guild {}: {}", channel.guild_id, e);
@@ -261,7 +909,108 @@ impl EventHandler for ClientHandler {
         
     }
 
+    async fn guild_member_addition(&self, ctx: Context, new_member: Member) {
+        let targetguild = new_member.guild_id;
+        if !self.punished.read().await.contains_key(&(targetguild, new_member.user.id.get() as i64)) {
+            return;
+        }
+        self.sender
+            .send(DBRequest {
+                request_type: DBRequestType::Rejoin,
+                command: Some(Command::Rejoin {
+                    targetguild,
+                    target: (new_member.user.clone(), Some(PartialMember::from(new_member))),
+                }),
+                context: Some(ctx),
+                threadlog: None,
+            })
+            .await
+            .unwrap_or_else(|e| {
+                eprintln!("Error sending Rejoin event {}", e);
+            });
+    }
+
+    async fn guild_ban_addition(&self, ctx: Context, guild_id: GuildId, banned_user: User) {
+        self.send_external(ctx, guild_id, (banned_user, None), ExternalChange::Ban).await;
+    }
+
+    async fn guild_ban_removal(&self, ctx: Context, guild_id: GuildId, unbanned_user: User) {
+        self.send_external(ctx, guild_id, (unbanned_user, None), ExternalChange::Unban).await;
+    }
+
+    async fn auto_moderation_action_execution(&self, ctx: Context, execution: ActionExecution) {
+        let action = match &execution.action {
+            AutoModAction::BlockMessage { .. } => "Blocked message".to_string(),
+            AutoModAction::Alert(channel) => format!("Alert in <#{}>", channel),
+            AutoModAction::Timeout(duration) => format!("Timeout for {}", format_duration(duration.as_secs() as i64)),
+            AutoModAction::Unknown(BLOCK_MEMBER_INTERACTION) => {
+                self.pending_quarantine.write().await.insert((execution.guild_id, execution.user_id), Timestamp::now());
+                "Blocked interactions".to_string()
+            }
+            AutoModAction::Unknown(kind) => format!("Action {}", kind),
+            _ => "Unknown action".to_string(),
+        };
+        let user = match execution.user_id.to_user(&ctx).await {
+            Ok(user) => user,
+            Err(e) => {
+                eprintln!("Error fetching user for AutoMod execution: {}", e);
+                return;
+            }
+        };
+        let member = execution.guild_id.member(&ctx.http, user.id).await.ok().map(PartialMember::from);
+        let rule = match execution.guild_id.automod_rule(&ctx.http, execution.rule_id).await {
+            Ok(rule) => rule.name,
+            Err(_) => execution.rule_id.to_string(),
+        };
+        let hit = AutoModHit {
+            rule_id: execution.rule_id.get(),
+            rule,
+            matched: execution.matched_content.clone().or(execution.matched_keyword.clone()).filter(|matched| !matched.is_empty()),
+            content: execution.content.clone(),
+            actions: vec![action],
+            channel: execution.channel_id,
+            time: Timestamp::now(),
+        };
+        self.send_automod(ctx, Command::AutoModHit {
+            targetguild: execution.guild_id,
+            target: (user, member),
+            hit,
+        }).await;
+    }
+
+    async fn guild_member_update(&self, ctx: Context, old_if_available: Option<Member>, new: Option<Member>, event: GuildMemberUpdateEvent) {
+        if let Some(quarantined) = self.quarantine_change(event.guild_id, event.user.id, old_if_available.as_ref(), event.flags).await {
+            self.send_automod(ctx.clone(), Command::Quarantine {
+                targetguild: event.guild_id,
+                target: (event.user.clone(), new.clone().map(PartialMember::from)),
+                quarantined,
+            }).await;
+        }
+
+        let now = Timestamp::now();
+        let until = event.communication_disabled_until.filter(|until| *until > now);
+        let before = old_if_available.as_ref().map(|old| old.communication_disabled_until.filter(|until| *until > now));
+        // Every member update repeats the current timeout, so only changes are passed on.
+        // Without a cached old member, lifted timeouts are only looked for on users with an active timeout on file,
+        // members muted by role (or the bot's own role update) would otherwise wait on the audit log for nothing.
+        let change = match (until, before) {
+            (Some(until), Some(before)) if before == Some(until) => return,
+            (Some(until), _) => ExternalChange::Timeout(until),
+            (None, Some(Some(_))) => ExternalChange::TimeoutRemoved,
+            (None, None) if self.punished.read().await.get(&(event.guild_id, event.user.id.get() as i64)) == Some(&true) => ExternalChange::TimeoutRemoved,
+            (None, _) => return,
+        };
+        let member = new.map(PartialMember::from);
+        self.send_external(ctx, event.guild_id, (event.user, member), change).await;
+    }
+
     async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
+        // Buttons and modals carry their target in the custom ID instead of options.
+        let interaction = match interaction {
+            Interaction::Component(component) => return self.component_interaction(ctx, component).await,
+            Interaction::Modal(modal) => return self.modal_interaction(ctx, modal).await,
+            interaction => interaction,
+        };
         if let Interaction::Command(command) = interaction {
             let targetguild = match command.guild_id {
                 Some(gid) => gid,
@@ -282,9 +1031,50 @@ impl EventHandler for ClientHandler {
             };
             let invoker = (command.user).clone();
             let mut opts = CommandOptions::default();
+            // Context menus target the right-clicked user instead of a user option.
+            if let Some(ResolvedTarget::User(user, member)) = command.data.target() {
+                opts.user = Some(user.clone());
+                opts.member = member.cloned();
+            }
             // Parse every current option
             for opt in command.data.options() {
                 match (opt.name, &opt.value) {
+                    // Subcommand names are shared with /punish and /schedule.
+                    (name, ResolvedValue::SubCommand(options)) if command.data.name == "escalation" => {
+                        opts.escalation = match name {
+                            "add" => Some(EscalationAction::Add),
+                            "threshold" => Some(EscalationAction::Threshold),
+                            "list" => Some(EscalationAction::List),
+                            "remove" => Some(EscalationAction::Remove),
+                            _ => None,
+                        };
+                        for subopt in options {
+                            match (subopt.name, &subopt.value) {
+                                ("trigger", ResolvedValue::String(t)) => {
+                                    opts.trigger = PunishmentType::from_name(t);
+                                }
+                                ("count", ResolvedValue::Integer(c)) => {
+                                    opts.count = Some(*c);
+                                }
+                                ("points", ResolvedValue::Integer(p)) => {
+                                    opts.points = Some(*p);
+                                }
+                                ("within", ResolvedValue::String(w)) => {
+                                    opts.within = Some((*w).to_string());
+                                }
+                                ("action", ResolvedValue::String(a)) => {
+                                    opts.punishment = PunishmentType::from_name(a);
+                                }
+                                ("duration", ResolvedValue::String(d)) => {
+                                    opts.duration = Some((*d).to_string());
+                                }
+                                ("id", ResolvedValue::String(i)) => {
+                                    opts.id = Some((*i).to_string());
+                                }
+                                _ => {}
+                            }
+                        }
+                    }
                     ("user", ResolvedValue::User(u, m)) => {
                         if let Some(m) = m {
                             opts.member = Some((**m).clone());
@@ -297,102 +1087,16 @@ impl EventHandler for ClientHandler {
                     ("allow", ResolvedValue::Boolean(a)) => {
                         opts.allow = Some(*a);
                     }
-                    ("add", ResolvedValue::SubCommandGroup { .. }) => {
+                    ("capability", ResolvedValue::String(c)) => {
+                        opts.capability = Capability::from_name(c);
+                    }
+                    ("add", ResolvedValue::SubCommandGroup(options)) => {
                         opts.action = Some(PunishmentAction::Add);
-                        if let ResolvedValue::SubCommandGroup(options) = &opt.value {
-                            //Should only be one subcommand here
-                            for subopt in options {
-                                match (subopt.name, &subopt.value) {
-                                    ("warn", ResolvedValue::SubCommand { .. }) => {
-                                        opts.punishment = Some(PunishmentType::Warn);
-                                        if let ResolvedValue::SubCommand(options) = &subopt.value {
-                                            for subopt2 in options {
-                                                match (subopt2.name, &subopt2.value) {
-                                                    ("user", ResolvedValue::User(u, m)) => {
-                                                        if let Some(m) = m {
-                                                            opts.member = Some((**m).clone());
-                                                        }
-                                                        opts.user = Some((**u).clone());
-                                                    }
-                                                    ("duration", ResolvedValue::String(d)) => {
-                                                        opts.duration = Some((*d).to_string());
-                                                    }
-                                                    ("reason", ResolvedValue::String(r)) => {
-                                                        opts.reason = Some((*r).to_string());
-                                                    }
-                                                    _ => {}
-                                                }
-                                            }
-                                        }
-                                    }
-                                    ("mute", ResolvedValue::SubCommand { .. }) => {
-                                        opts.punishment = Some(PunishmentType::Mute);
-                                        if let ResolvedValue::SubCommand(options) = &subopt.value {
-                                            for subopt2 in options {
-                                                match (subopt2.name, &subopt2.value) {
-                                                    ("user", ResolvedValue::User(u, m)) => {
-                                                        if let Some(m) = m {
-                                                            opts.member = Some((**m).clone());
-                                                        }
-                                                        opts.user = Some((**u).clone());
-                                                    }
-                                                        ("duration", ResolvedValue::String(d)) => {
-                                                        opts.duration = Some((*d).to_string());
-                                                    }
-                                                    ("reason", ResolvedValue::String(r)) => {
-                                                        opts.reason = Some((*r).to_string());
-                                                    }
-                                                    _ => {}
-                                                }
-                                            }
-                                        }
-                                    }
-                                    ("timeout", ResolvedValue::SubCommand { .. }) => {
-                                        opts.punishment = Some(PunishmentType::Timeout);
-                                        if let ResolvedValue::SubCommand(options) = &subopt.value {
-                                            for subopt2 in options {
-                                                match (subopt2.name, &subopt2.value) {
-                                                    ("user", ResolvedValue::User(u, m)) => {
-                                                        if let Some(m) = m {
-                                                            opts.member = Some((**m).clone());
-                                                        }
-                                                        opts.user = Some((**u).clone());
-                                                    }
-                                                    ("duration", ResolvedValue::String(d)) => {
-                                                        opts.duration = Some((*d).to_string());
-                                                    }
-                                                    ("reason", ResolvedValue::String(r)) => {
-                                                        opts.reason = Some((*r).to_string());
-                                                    }
-                                                    _ => {}
-                                                }
-                                            }
-                                        }
-                                    }
-                                    ("ban", ResolvedValue::SubCommand { .. }) => {
-                                        opts.punishment = Some(PunishmentType::Ban);
-                                        if let ResolvedValue::SubCommand(options) = &subopt.value {
-                                            for subopt2 in options {
-                                                match (subopt2.name, &subopt2.value) {
-                                                    ("user", ResolvedValue::User(u, m)) => {
-                                                        if let Some(m) = m {
-                                                            opts.member = Some((**m).clone());
-                                                        }
-                                                        opts.user = Some((**u).clone());
-                                                    }
-                                                    ("duration", ResolvedValue::String(d)) => {
-                                                        opts.duration = Some((*d).to_string());
-                                                    }
-                                                    ("reason", ResolvedValue::String(r)) => {
-                                                        opts.reason = Some((*r).to_string());
-                                                    }
-                                                    _ => {}
-                                                }
-                                            }
-                                        }
-                                    }
-                                    _ => {}
-                                }
+                        //Should only be one subcommand here, named after the punishment type
+                        for subopt in options {
+                            if let ResolvedValue::SubCommand(options) = &subopt.value {
+                                opts.punishment = PunishmentType::from_name(subopt.name);
+                                ClientHandler::parse_add_options(&mut opts, options);
                             }
                         }
                     }
@@ -413,6 +1117,9 @@ impl EventHandler for ClientHandler {
                                     ("id", ResolvedValue::String(i)) => {
                                         opts.id = Some((*i).to_string());
                                     }
+                                    ("notify", ResolvedValue::Boolean(n)) => {
+                                        opts.notify = Some(*n);
+                                    }
                                     _ => {}
                                 }
                             }
@@ -441,6 +1148,147 @@ impl EventHandler for ClientHandler {
                                     ("reason", ResolvedValue::String(r)) => {
                                         opts.reason = Some((*r).to_string());
                                     }
+                                    ("notify", ResolvedValue::Boolean(n)) => {
+                                        opts.notify = Some(*n);
+                                    }
+                                    ("evidence", ResolvedValue::Attachment(a)) => {
+                                        opts.attachment = Some((*a).clone());
+                                    }
+                                    ("message_link", ResolvedValue::String(l)) => {
+                                        opts.message_link = Some((*l).to_string());
+                                    }
+                                    _ => {}
+                                }
+                            }
+                        }
+                    }
+                    ("view", ResolvedValue::SubCommand { .. }) => {
+                        opts.modconfig = Some(ConfigAction::View);
+                    }
+                    ("set", ResolvedValue::SubCommand { .. }) => {
+                        opts.modconfig = Some(ConfigAction::Set);
+                        if let ResolvedValue::SubCommand(options) = &opt.value {
+                            for subopt in options {
+                                match (subopt.name, &subopt.value) {
+                                    ("log_channel", ResolvedValue::Channel(c)) => {
+                                        opts.config.log_channel = Some(c.id);
+                                    }
+                                    ("notifier_channel", ResolvedValue::Channel(c)) => {
+                                        opts.config.notifier_channel = Some(c.id);
+                                    }
+                                    ("appeal_channel", ResolvedValue::Channel(c)) => {
+                                        opts.config.appeal_channel = Some(c.id);
+                                    }
+                                    ("mute_role", ResolvedValue::Role(r)) => {
+                                        opts.config.mute_role = Some(r.id);
+                                    }
+                                    ("dm_default", ResolvedValue::Boolean(d)) => {
+                                        opts.config.dm_default = Some(*d);
+                                    }
+                                    ("ban_color", ResolvedValue::String(c)) => {
+                                        opts.config.ban_color = Some((*c).to_string());
+                                    }
+                                    ("mute_color", ResolvedValue::String(c)) => {
+                                        opts.config.mute_color = Some((*c).to_string());
+                                    }
+                                    ("timeout_color", ResolvedValue::String(c)) => {
+                                        opts.config.timeout_color = Some((*c).to_string());
+                                    }
+                                    ("appeal", ResolvedValue::String(a)) => {
+                                        opts.config.appeal = Some((*a).to_string());
+                                    }
+                                    ("archive", ResolvedValue::Boolean(a)) => {
+                                        opts.config.archive = Some(*a);
+                                    }
+                                    ("archive_max_size", ResolvedValue::Integer(m)) => {
+                                        opts.config.archive_max_size = Some(*m);
+                                    }
+                                    ("archive_retention", ResolvedValue::Integer(r)) => {
+                                        opts.config.archive_retention = Some(*r);
+                                    }
+                                    ("warn_decay", ResolvedValue::Integer(d)) => {
+                                        opts.config.warn_decay = Some(*d);
+                                    }
+                                    ("mute_decay", ResolvedValue::Integer(d)) => {
+                                        opts.config.mute_decay = Some(*d);
+                                    }
+                                    ("timeout_decay", ResolvedValue::Integer(d)) => {
+                                        opts.config.timeout_decay = Some(*d);
+                                    }
+                                    ("kick_decay", ResolvedValue::Integer(d)) => {
+                                        opts.config.kick_decay = Some(*d);
+                                    }
+                                    ("softban_decay", ResolvedValue::Integer(d)) => {
+                                        opts.config.softban_decay = Some(*d);
+                                    }
+                                    ("ban_decay", ResolvedValue::Integer(d)) => {
+                                        opts.config.ban_decay = Some(*d);
+                                    }
+                                    ("warn_points", ResolvedValue::Integer(p)) => {
+                                        opts.config.warn_points = Some(*p);
+                                    }
+                                    ("mute_points", ResolvedValue::Integer(p)) => {
+                                        opts.config.mute_points = Some(*p);
+                                    }
+                                    ("timeout_points", ResolvedValue::Integer(p)) => {
+                                        opts.config.timeout_points = Some(*p);
+                                    }
+                                    ("kick_points", ResolvedValue::Integer(p)) => {
+                                        opts.config.kick_points = Some(*p);
+                                    }
+                                    ("softban_points", ResolvedValue::Integer(p)) => {
+                                        opts.config.softban_points = Some(*p);
+                                    }
+                                    ("ban_points", ResolvedValue::Integer(p)) => {
+                                        opts.config.ban_points = Some(*p);
+                                    }
+                                    _ => {}
+                                }
+                            }
+                        }
+                    }
+                    ("get", ResolvedValue::SubCommand { .. }) => {
+                        if let ResolvedValue::SubCommand(options) = &opt.value {
+                            for subopt in options {
+                                match (subopt.name, &subopt.value) {
+                                    ("user", ResolvedValue::User(u, m)) => {
+                                        if let Some(m) = m {
+                                            opts.member = Some((**m).clone());
+                                        }
+                                        opts.user = Some((**u).clone());
+                                    }
+                                    ("case", ResolvedValue::String(c)) => {
+                                        opts.id = Some((*c).to_string());
+                                    }
+                                    _ => {}
+                                }
+                            }
+                        }
+                    }
+                    ("list", ResolvedValue::SubCommand { .. }) => {
+                        opts.schedule = Some(ScheduleAction::List);
+                    }
+                    ("cancel", ResolvedValue::SubCommand { .. }) => {
+                        opts.schedule = Some(ScheduleAction::Cancel);
+                        if let ResolvedValue::SubCommand(options) = &opt.value {
+                            for subopt in options {
+                                if let ("id", ResolvedValue::String(i)) = (subopt.name, &subopt.value) {
+                                    opts.id = Some((*i).to_string());
+                                }
+                            }
+                        }
+                    }
+                    ("remind", ResolvedValue::SubCommand { .. }) => {
+                        opts.schedule = Some(ScheduleAction::Remind);
+                        if let ResolvedValue::SubCommand(options) = &opt.value {
+                            for subopt in options {
+                                match (subopt.name, &subopt.value) {
+                                    ("duration", ResolvedValue::String(d)) => {
+                                        opts.duration = Some((*d).to_string());
+                                    }
+                                    ("message", ResolvedValue::String(m)) => {
+                                        opts.message = Some((*m).to_string());
+                                    }
                                     _ => {}
                                 }
                             }
@@ -449,7 +1297,72 @@ impl EventHandler for ClientHandler {
                     _ => {}
                 }
             }
-            match command.data.name.as_str() {
+            // User context menus stand in for the slash command they shortcut.
+            let name = match command.data.name.as_str() {
+                PROFILE_MENU => "fetchprofile",
+                WARN_MENU => {
+                    opts.action = Some(PunishmentAction::Add);
+                    opts.punishment = Some(PunishmentType::Warn);
+                    "punish"
+                }
+                TIMEOUT_MENU => {
+                    opts.action = Some(PunishmentAction::Add);
+                    opts.punishment = Some(PunishmentType::Timeout);
+                    opts.duration = Some("1h".to_string());
+                    "punish"
+                }
+                PUNISH_MENU => {
+                    // The punishment type is only known once the modal is submitted, which is where it's authorized.
+                    if let Some(user) = &opts.user {
+                        let modal = punish_modal(targetguild, user.id.get() as i64, &user.name, None);
+                        if let Err(e) = command.create_response(&ctx.http, CreateInteractionResponse::Modal(modal)).await {
+                            eprintln!("Error opening punish modal {}", e);
+                        }
+                    }
+                    return;
+                }
+                PUNISH_AUTHOR_MENU => {
+                    // Snapshot now, the message may be deleted before the modal is submitted.
+                    if let Some(ResolvedTarget::Message(message)) = command.data.target() {
+                        let now = Timestamp::now().unix_timestamp();
+                        let mut pending = self.pending_evidence.write().await;
+                        pending.retain(|_, (opened, _)| now - opened.unix_timestamp() < PENDING_EVIDENCE_TTL);
+                        pending.insert((message.id, command.user.id), (Timestamp::now(), Evidence::Message(MessageSnapshot::from_message(message, targetguild))));
+                        drop(pending);
+
+                        let modal = punish_modal(targetguild, message.author.id.get() as i64, &message.author.name, Some(message.id));
+                        if let Err(e) = command.create_response(&ctx.http, CreateInteractionResponse::Modal(modal)).await {
+                            eprintln!("Error opening punish modal {}", e);
+                        }
+                    }
+                    return;
+                }
+                name => name,
+            };
+            // Enforced here as Discord's own overrides can only gate whole commands.
+            let capability = required_capability(name, &opts);
+            let allowed = match &command.member {
+                Some(member) => authorized(self.roles.read().await.get(&targetguild), member, capability),
+                None => false,
+            };
+            if !allowed {
+                command
+                    .create_response(
+                        &ctx.http,
+                        CreateInteractionResponse::Message(
+                            CreateInteractionResponseMessage::new()
+                                .content(match capability {
+                                    Some(capability) => format!("You lack the {:?} permission required for this command.", capability),
+                                    None => "Only Administrators can use this command.".to_string(),
+                                })
+                                .ephemeral(true),
+                        ),
+                    )
+                    .await
+                    .expect("Failed to send response");
+                return;
+            }
+            match name {
                 "punish" => {
                     let (user, member) = match (opts.user, opts.member) {
                         (Some(u), Some(m)) => (u, Some(m)),
@@ -470,35 +1383,97 @@ impl EventHandler for ClientHandler {
                         }
                     };
 
-                    let length = match (opts.duration) {
-                        (Some(duration)) => {
-                            ClientHandler::millis(duration)
+                    let length = match opts.duration.as_deref().map(parse_duration) {
+                        Some(Ok(length)) => Some(length),
+                        Some(Err(e)) => {
+                            command
+                                .create_response(
+                                    &ctx.http,
+                                    CreateInteractionResponse::Message(
+                                        CreateInteractionResponseMessage::new()
+                                            .content(e)
+                                            .ephemeral(true),
+                                    ),
+                                )
+                                .await
+                                .expect("Failed to send response");
+                            return;
+                        }
+                        None => None,
+                    };
+
+                    if matches!(opts.punishment, Some(PunishmentType::Timeout))
+                        && length.and_then(|l| l.seconds_from_now()).is_none_or(|s| s > MAX_TIMEOUT)
+                    {
+                        command
+                            .create_response(
+                                &ctx.http,
+                                CreateInteractionResponse::Message(
+                                    CreateInteractionResponseMessage::new()
+                                        .content("Timeouts must end within 28 days.")
+                                        .ephemeral(true),
+                                ),
+                            )
+                            .await
+                            .expect("Failed to send response");
+                        return;
+                    }
+
+                    let purge = match opts.purge.as_deref().map(PurgeScope::parse).transpose() {
+                        Ok(purge) => purge,
+                        Err(e) => {
+                            command
+                                .create_response(&ctx.http, ClientHandler::ephemeral(&e))
+                                .await
+                                .expect("Failed to send response");
+                            return;
+                        }
+                    };
+
+                    let evidence = match ClientHandler::collect_evidence(&ctx, targetguild, opts.attachment.take(), opts.message_link.take()).await {
+                        Ok(evidence) => evidence,
+                        Err(e) => {
+                            command
+                                .create_response(&ctx.http, ClientHandler::ephemeral(e))
+                                .await
+                                .expect("Failed to send response");
+                            return;
                         }
-                        _ => None,
                     };
 
                     match opts.action {
                         Some(PunishmentAction::Add) => {
                             if let Some(punishment) = opts.punishment {
+                                let delete_days = opts.delete_days.map_or(punishment.default_delete_days(), |days| days.clamp(0, 7) as u8);
+                                let (recorded, record) = oneshot::channel();
+                                let target = (user, member);
                                 self.sender
                                     .send(DBRequest {
                                         request_type: DBRequestType::Punishment,
                                         command: Some(Command::PunishAdd {
-                                            command,
+                                            command: command.clone().into(),
                                             targetguild,
-                                            target: (user, member),
+                                            target: target.clone(),
                                             invoker,
                                             ptype: punishment,
                                             reason: opts.reason,
                                             length,
+                                            notify: opts.notify,
+                                            evidence,
+                                            delete_days,
+                                            recorded: purge.is_some().then_some(recorded),
                                         }),
-                                        context: Some(ctx),
+                                        context: Some(ctx.clone()),
                                         threadlog: None,
                                     })
                                     .await
                                     .unwrap_or_else(|e| {
                                         eprintln!("Error sending Punishment event {}", e);
                                     });
+                                // The record has to exist before anything is deleted, a punishment that fails leaves the messages alone.
+                                if let (Some(scope), Ok(id)) = (purge, record.await) {
+                                    self.purge_after(ctx, &command, targetguild, target, id, scope).await;
+                                }
                             }
                         }
                         Some(PunishmentAction::Remove) => {
@@ -506,13 +1481,14 @@ impl EventHandler for ClientHandler {
                                 .send(DBRequest {
                                     request_type: DBRequestType::Punishment,
                                     command: Some(Command::PunishRemove {
-                                        command,
+                                        command: command.into(),
                                         targetguild,
                                         target: (user, member),
                                         invoker,
                                         latest: opts.latest,
                                         id: opts.id,
                                         silent: false,
+                                        notify: opts.notify,
                                     }),
                                     context: Some(ctx),
                                     threadlog: None,
@@ -528,7 +1504,7 @@ impl EventHandler for ClientHandler {
                                     request_type: DBRequestType::Punishment,
                                     command: (
                                         Some(Command::PunishEdit {
-                                            command,
+                                            command: command.into(),
                                             targetguild,
                                             target: (user, member),
                                             invoker,
@@ -536,6 +1512,8 @@ impl EventHandler for ClientHandler {
                                             length,
                                             latest: opts.latest,
                                             id: opts.id,
+                                            notify: opts.notify,
+                                            evidence,
                                         })
                                     ),
                                     context: Some(ctx),
@@ -586,10 +1564,11 @@ impl EventHandler for ClientHandler {
                             request_type: DBRequestType::FetchProfile,
                             command: (
                                 Some(Command::FetchProfile { 
-                                    command, 
+                                    command: command.into(), 
                                     targetguild, 
                                     target: (user,member), 
-                                    invoker
+                                    invoker,
+                                    page: 0,
                                 })
                             ),
                             context: Some(ctx),
@@ -601,7 +1580,196 @@ impl EventHandler for ClientHandler {
                         });
                     return;
                 }
-                "roleset" => {}
+                "evidence" => {
+                    let (Some(user), Some(id)) = (opts.user, opts.id) else {
+                        command
+                            .create_response(&ctx.http, ClientHandler::ephemeral("Missing user or case option."))
+                            .await
+                            .expect("Failed to send response");
+                        return;
+                    };
+                    self.sender
+                        .send(DBRequest {
+                            request_type: DBRequestType::Evidence,
+                            command: Some(Command::EvidenceGet {
+                                command,
+                                targetguild,
+                                target: (user, opts.member),
+                                id,
+                            }),
+                            context: Some(ctx),
+                            threadlog: None,
+                        })
+                        .await
+                        .unwrap_or_else(|e| {
+                            eprintln!("Error sending Evidence event {}", e);
+                        });
+                }
+                "escalation" => {
+                    let escalationcmd = match opts.escalation {
+                        Some(EscalationAction::List) => Command::EscalationList { command, targetguild },
+                        Some(EscalationAction::Remove) => Command::EscalationRemove {
+                            command,
+                            targetguild,
+                            id: opts.id.unwrap_or_default(),
+                        },
+                        Some(action @ (EscalationAction::Add | EscalationAction::Threshold)) => {
+                            let rule = match (action, opts.trigger, opts.count, opts.points, opts.punishment) {
+                                (EscalationAction::Add, Some(trigger), Some(count), _, Some(action)) => {
+                                    EscalationRule::new(trigger, count, opts.within.as_deref(), action, opts.duration.as_deref())
+                                }
+                                (EscalationAction::Threshold, _, _, Some(points), Some(action)) => {
+                                    EscalationRule::threshold(points, action, opts.duration.as_deref())
+                                }
+                                _ => Err("Missing a required option.".to_string()),
+                            };
+                            match rule {
+                                Ok(rule) => Command::EscalationAdd { command, targetguild, invoker, rule },
+                                Err(e) => {
+                                    command
+                                        .create_response(&ctx.http, ClientHandler::ephemeral(&e))
+                                        .await
+                                        .expect("Failed to send response");
+                                    return;
+                                }
+                            }
+                        }
+                        None => return,
+                    };
+                    self.sender
+                        .send(DBRequest {
+                            request_type: DBRequestType::Escalation,
+                            command: Some(escalationcmd),
+                            context: Some(ctx),
+                            threadlog: None,
+                        })
+                        .await
+                        .unwrap_or_else(|e| {
+                            eprintln!("Error sending Escalation event {}", e);
+                        });
+                }
+                "roleset" => {
+                    let (role, allow) = match (opts.role, opts.allow) {
+                        (Some(role), Some(allow)) => (role, allow),
+                        _ => {
+                            command
+                                .create_response(
+                                    &ctx.http,
+                                    CreateInteractionResponse::Message(
+                                        CreateInteractionResponseMessage::new()
+                                            .content("Missing role or allow option.")
+                                            .ephemeral(true),
+                                    ),
+                                )
+                                .await
+                                .expect("Failed to send response");
+                            return;
+                        }
+                    };
+                    self.sender
+                        .send(DBRequest {
+                            request_type: DBRequestType::CommandPermissionUpdate,
+                            command: Some(Command::RoleAdjust {
+                                command,
+                                targetguild,
+                                target: role,
+                                invoker,
+                                allow,
+                                capability: opts.capability,
+                            }),
+                            context: Some(ctx),
+                            threadlog: None,
+                        })
+                        .await
+                        .unwrap_or_else(|e| {
+                            eprintln!("Error sending Role Permission event {}", e);
+                        });
+                }
+                "modconfig" => {
+                    let configcmd = match opts.modconfig {
+                        Some(ConfigAction::View) => Command::ModConfigView { command, targetguild },
+                        Some(ConfigAction::Set) => Command::ModConfigSet {
+                            command,
+                            targetguild,
+                            invoker,
+                            changes: opts.config,
+                        },
+                        None => return,
+                    };
+                    self.sender
+                        .send(DBRequest {
+                            request_type: DBRequestType::Config,
+                            command: Some(configcmd),
+                            context: Some(ctx),
+                            threadlog: None,
+                        })
+                        .await
+                        .unwrap_or_else(|e| {
+                            eprintln!("Error sending Config event {}", e);
+                        });
+                }
+                "schedule" => {
+                    let schedulecmd = match opts.schedule {
+                        Some(ScheduleAction::List) => Command::ScheduleList { command, targetguild },
+                        Some(ScheduleAction::Cancel) => Command::ScheduleCancel {
+                            command,
+                            targetguild,
+                            id: opts.id.unwrap_or_default(),
+                        },
+                        Some(ScheduleAction::Remind) => {
+                            match opts.duration.as_deref().map(parse_duration) {
+                                Some(Ok(length)) if length != Length::Permanent => Command::ScheduleRemind {
+                                    channel: command.channel_id,
+                                    command,
+                                    targetguild,
+                                    invoker,
+                                    length,
+                                    message: opts.message.unwrap_or_default(),
+                                },
+                                Some(Err(e)) => {
+                                    command
+                                        .create_response(
+                                            &ctx.http,
+                                            CreateInteractionResponse::Message(
+                                                CreateInteractionResponseMessage::new()
+                                                    .content(e)
+                                                    .ephemeral(true),
+                                            ),
+                                        )
+                                        .await
+                                        .expect("Failed to send response");
+                                    return;
+                                }
+                                _ => {
+                                    command
+                                        .create_response(
+                                            &ctx.http,
+                                            CreateInteractionResponse::Message(
+                                                CreateInteractionResponseMessage::new()
+                                                    .content("Reminders need a duration, i.e. 10m, 5h or until 2026-12-01 18:00.")
+                                                    .ephemeral(true),
+                                            ),
+                                        )
+                                        .await
+                                        .expect("Failed to send response");
+                                    return;
+                                }
+                            }
+                        }
+                        None => return,
+                    };
+                    self.sender
+                        .send(DBRequest {
+                            request_type: DBRequestType::Schedule,
+                            command: Some(schedulecmd),
+                            context: Some(ctx),
+                            threadlog: None,
+                        })
+                        .await
+                        .unwrap_or_else(|e| {
+                            eprintln!("Error sending Schedule event {}", e);
+                        });
+                }
                 _ => {
                     return;
                 }
@@ -1,16 +1,34 @@
 use serenity::{
-    all::{Integration, PartialMember, Role, User}, 
+    all::{Attachment, Integration, PartialMember, Role, User}, 
     builder::{CreateCommand, CreateCommandOption}, 
-    model::{application::{CommandOptionType, InstallationContext, InteractionContext},Permissions}
+    model::{application::{CommandOptionType, CommandType, InstallationContext, InteractionContext},channel::ChannelType,Permissions}
 };
 use serde::{Serialize, Deserialize};
+use crate::db::ConfigChanges;
 
 pub enum ModbotCmd {
     FetchProfile,
     Punishment,
     RoleSet,
+    Schedule,
+    ModConfig,
+    Evidence,
+    Escalation,
+    ProfileMenu,
+    WarnMenu,
+    TimeoutMenu,
+    PunishMenu,
+    PunishAuthorMenu,
 }
 
+// User context menu names, these are shown to moderators as written.
+pub const PROFILE_MENU: &str = "Moderation profile";
+pub const WARN_MENU: &str = "Warn";
+pub const TIMEOUT_MENU: &str = "Timeout 1h";
+pub const PUNISH_MENU: &str = "Punish…";
+// Message context menu name.
+pub const PUNISH_AUTHOR_MENU: &str = "Punish author";
+
 //Reference of all values known in commands
 #[derive(Default)]
 pub struct CommandOptions {
@@ -22,16 +40,109 @@ pub struct CommandOptions {
     pub duration: Option<String>,
     pub id: Option<String>,
     pub latest: Option<bool>,
+    pub notify: Option<bool>,
+    pub attachment: Option<Attachment>,
+    pub message_link: Option<String>,
     pub punishment: Option<PunishmentType>,
     pub action: Option<PunishmentAction>,
+    pub schedule: Option<ScheduleAction>,
+    pub message: Option<String>,
+    pub capability: Option<Capability>,
+    pub modconfig: Option<ConfigAction>,
+    pub config: ConfigChanges,
+    pub escalation: Option<EscalationAction>,
+    pub trigger: Option<PunishmentType>,
+    pub count: Option<i64>,
+    pub delete_days: Option<i64>,
+    pub purge: Option<String>,
+    pub points: Option<i64>,
+    pub within: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub enum PunishmentType {
     Warn,
     Mute,
     Ban,
     Timeout,
+    Kick, //Instantaneous, recorded with the same start and end
+    Softban, //Instantaneous ban and unban that deletes recent messages
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Capability {
+    Fetch,
+    Warn,
+    Mute,
+    Timeout,
+    Kick,
+    Ban,
+    Edit,
+    Remove,
+    RoleSet,
+}
+
+impl Capability {
+    pub const ALL: [Capability; 9] = [
+        Capability::Fetch,
+        Capability::Warn,
+        Capability::Mute,
+        Capability::Timeout,
+        Capability::Kick,
+        Capability::Ban,
+        Capability::Edit,
+        Capability::Remove,
+        Capability::RoleSet,
+    ];
+
+    pub fn from_name(name: &str) -> Option<Capability> {
+        match name {
+            "fetch" => Some(Capability::Fetch),
+            "warn" => Some(Capability::Warn),
+            "mute" => Some(Capability::Mute),
+            "timeout" => Some(Capability::Timeout),
+            "kick" => Some(Capability::Kick),
+            "ban" => Some(Capability::Ban),
+            "edit" => Some(Capability::Edit),
+            "remove" => Some(Capability::Remove),
+            "roleset" => Some(Capability::RoleSet),
+            _ => None,
+        }
+    }
+
+    pub fn for_punishment(ptype: &PunishmentType) -> Capability {
+        match ptype {
+            PunishmentType::Warn => Capability::Warn,
+            PunishmentType::Mute => Capability::Mute,
+            PunishmentType::Timeout => Capability::Timeout,
+            PunishmentType::Kick => Capability::Kick,
+            PunishmentType::Softban | PunishmentType::Ban => Capability::Ban,
+        }
+    }
+}
+
+impl PunishmentType {
+    pub fn from_name(name: &str) -> Option<PunishmentType> {
+        match name.trim().to_lowercase().as_str() {
+            "warn" => Some(PunishmentType::Warn),
+            "mute" => Some(PunishmentType::Mute),
+            "timeout" => Some(PunishmentType::Timeout),
+            "kick" => Some(PunishmentType::Kick),
+            "softban" => Some(PunishmentType::Softban),
+            "ban" => Some(PunishmentType::Ban),
+            _ => None,
+        }
+    }
+
+    // Instant punishments happen once and are recorded without a period, so durations don't apply to them.
+    pub fn is_instant(&self) -> bool {
+        matches!(self, PunishmentType::Kick | PunishmentType::Softban)
+    }
+
+    // Days of messages deleted when none are given, clearing them is what a softban is for.
+    pub fn default_delete_days(&self) -> u8 {
+        if *self == PunishmentType::Softban { 1 } else { 0 }
+    }
 }
 
 pub enum PunishmentAction {
@@ -40,9 +151,63 @@ pub enum PunishmentAction {
     Edit,
 }
 
+pub enum ScheduleAction {
+    List,
+    Cancel,
+    Remind,
+}
+
+pub enum ConfigAction {
+    View,
+    Set,
+}
+
+pub enum EscalationAction {
+    Add,
+    Threshold,
+    List,
+    Remove,
+}
+
 impl ModbotCmd {
+    // Context menu commands take no options or description, the target is the right-clicked user or message.
+    fn user_menu(name: &str) -> CreateCommand {
+        CreateCommand::new(name)
+            .kind(CommandType::User)
+            .default_member_permissions(Permissions::ADMINISTRATOR)
+            .add_context(InteractionContext::Guild)
+            .add_integration_type(InstallationContext::Guild)
+    }
+
+    fn punishment_choice(name: &str, description: &str) -> CreateCommandOption {
+        CreateCommandOption::new(CommandOptionType::String, name, description)
+            .add_string_choice("Warn", "warn")
+            .add_string_choice("Mute", "mute")
+            .add_string_choice("Timeout", "timeout")
+            .add_string_choice("Kick", "kick")
+            .add_string_choice("Softban", "softban")
+            .add_string_choice("Ban", "ban")
+    }
+
+    fn decay_option(name: &str, description: &str) -> CreateCommandOption {
+        CreateCommandOption::new(CommandOptionType::Integer, name, description)
+            .min_int_value(0)
+            .max_int_value(3650)
+    }
+
+    fn points_option(name: &str, description: &str) -> CreateCommandOption {
+        CreateCommandOption::new(CommandOptionType::Integer, name, description)
+            .min_int_value(0)
+            .max_int_value(100)
+    }
+
     pub fn build(&self) -> CreateCommand {
         match self {
+            ModbotCmd::ProfileMenu => ModbotCmd::user_menu(PROFILE_MENU),
+            ModbotCmd::WarnMenu => ModbotCmd::user_menu(WARN_MENU),
+            ModbotCmd::TimeoutMenu => ModbotCmd::user_menu(TIMEOUT_MENU),
+            ModbotCmd::PunishMenu => ModbotCmd::user_menu(PUNISH_MENU),
+            ModbotCmd::PunishAuthorMenu => ModbotCmd::user_menu(PUNISH_AUTHOR_MENU).kind(CommandType::Message),
             ModbotCmd::FetchProfile => 
                 CreateCommand::new("fetchprofile")
                     .default_member_permissions(Permissions::ADMINISTRATOR)
@@ -80,7 +245,7 @@ impl ModbotCmd {
                             .add_sub_option(CreateCommandOption::new(
                                 CommandOptionType::String,
                                 "duration",
-                                "Duration of punishment (i.e. 10m, 5h, 2d)")
+                                "Duration of punishment (i.e. 10m, 1h30m, 2w, perm, until 2026-12-01 18:00)")
                                 .required(true)
                             )
                             .add_sub_option(CreateCommandOption::new(
@@ -88,6 +253,22 @@ impl ModbotCmd {
                                 "reason",
                                 "Reason for timeout") 
                                 .max_length(512))
+                            .add_sub_option(CreateCommandOption::new(
+                                CommandOptionType::Boolean,
+                                "notify",
+                                "Direct message the user about this (defaults to the server setting)"))
+                            .add_sub_option(CreateCommandOption::new(
+                                CommandOptionType::Attachment,
+                                "evidence",
+                                "Screenshot or file to keep with the punishment"))
+                            .add_sub_option(CreateCommandOption::new(
+                                CommandOptionType::String,
+                                "message_link",
+                                "Link to a message to keep with the punishment"))
+                            .add_sub_option(CreateCommandOption::new(
+                                CommandOptionType::String,
+                                "purge",
+                                "Delete the user's recent messages, a count like 50 or a length like 1h"))
                         )
                         .add_sub_option(CreateCommandOption::new(
                             CommandOptionType::SubCommand,
@@ -104,6 +285,22 @@ impl ModbotCmd {
                                 "reason",
                                 "Reason for warn") 
                                 .max_length(512))
+                            .add_sub_option(CreateCommandOption::new(
+                                CommandOptionType::Boolean,
+                                "notify",
+                                "Direct message the user about this (defaults to the server setting)"))
+                            .add_sub_option(CreateCommandOption::new(
+                                CommandOptionType::Attachment,
+                                "evidence",
+                                "Screenshot or file to keep with the punishment"))
+                            .add_sub_option(CreateCommandOption::new(
+                                CommandOptionType::String,
+                                "message_link",
+                                "Link to a message to keep with the punishment"))
+                            .add_sub_option(CreateCommandOption::new(
+                                CommandOptionType::String,
+                                "purge",
+                                "Delete the user's recent messages, a count like 50 or a length like 1h"))
                         )
                         .add_sub_option(CreateCommandOption::new(
                             CommandOptionType::SubCommand,
@@ -118,7 +315,7 @@ impl ModbotCmd {
                             .add_sub_option(CreateCommandOption::new(
                                 CommandOptionType::String,
                                 "duration",
-                                "Duration of punishment (i.e. 10m, 5h, 2d)")
+                                "Duration of punishment (i.e. 10m, 1h30m, 2w, perm, until 2026-12-01 18:00)")
                                 .required(true)
                             )
                             .add_sub_option(CreateCommandOption::new(
@@ -126,6 +323,92 @@ impl ModbotCmd {
                                 "reason",
                                 "Reason for punishment") 
                                 .max_length(512))
+                            .add_sub_option(CreateCommandOption::new(
+                                CommandOptionType::Boolean,
+                                "notify",
+                                "Direct message the user about this (defaults to the server setting)"))
+                            .add_sub_option(CreateCommandOption::new(
+                                CommandOptionType::Attachment,
+                                "evidence",
+                                "Screenshot or file to keep with the punishment"))
+                            .add_sub_option(CreateCommandOption::new(
+                                CommandOptionType::String,
+                                "message_link",
+                                "Link to a message to keep with the punishment"))
+                            .add_sub_option(CreateCommandOption::new(
+                                CommandOptionType::String,
+                                "purge",
+                                "Delete the user's recent messages, a count like 50 or a length like 1h"))
+                        )
+                        .add_sub_option(CreateCommandOption::new(
+                            CommandOptionType::SubCommand,
+                            "kick",
+                            "Kick a user")
+                            .add_sub_option(CreateCommandOption::new(
+                                CommandOptionType::User,
+                                "user",
+                                "The user to punish") 
+                                .required(true)
+                                .set_autocomplete(true)) 
+                            .add_sub_option(CreateCommandOption::new(
+                                CommandOptionType::String,
+                                "reason",
+                                "Reason for kick") 
+                                .max_length(512))
+                            .add_sub_option(CreateCommandOption::new(
+                                CommandOptionType::Boolean,
+                                "notify",
+                                "Direct message the user about this (defaults to the server setting)"))
+                            .add_sub_option(CreateCommandOption::new(
+                                CommandOptionType::Attachment,
+                                "evidence",
+                                "Screenshot or file to keep with the punishment"))
+                            .add_sub_option(CreateCommandOption::new(
+                                CommandOptionType::String,
+                                "message_link",
+                                "Link to a message to keep with the punishment"))
+                            .add_sub_option(CreateCommandOption::new(
+                                CommandOptionType::String,
+                                "purge",
+                                "Delete the user's recent messages, a count like 50 or a length like 1h"))
+                        )
+                        .add_sub_option(CreateCommandOption::new(
+                            CommandOptionType::SubCommand,
+                            "softban",
+                            "Ban and immediately unban a user to delete their recent messages")
+                            .add_sub_option(CreateCommandOption::new(
+                                CommandOptionType::User,
+                                "user",
+                                "The user to punish") 
+                                .required(true)
+                                .set_autocomplete(true)) 
+                            .add_sub_option(CreateCommandOption::new(
+                                CommandOptionType::Integer,
+                                "delete_days",
+                                "Days of messages to delete (defaults to 1)")
+                                .min_int_value(0)
+                                .max_int_value(7))
+                            .add_sub_option(CreateCommandOption::new(
+                                CommandOptionType::String,
+                                "reason",
+                                "Reason for softban") 
+                                .max_length(512))
+                            .add_sub_option(CreateCommandOption::new(
+                                CommandOptionType::Boolean,
+                                "notify",
+                                "Direct message the user about this (defaults to the server setting)"))
+                            .add_sub_option(CreateCommandOption::new(
+                                CommandOptionType::Attachment,
+                                "evidence",
+                                "Screenshot or file to keep with the punishment"))
+                            .add_sub_option(CreateCommandOption::new(
+                                CommandOptionType::String,
+                                "message_link",
+                                "Link to a message to keep with the punishment"))
+                            .add_sub_option(CreateCommandOption::new(
+                                CommandOptionType::String,
+                                "purge",
+                                "Delete the user's recent messages, a count like 50 or a length like 1h"))
                         )
                         .add_sub_option(CreateCommandOption::new(
                             CommandOptionType::SubCommand,
@@ -140,13 +423,35 @@ impl ModbotCmd {
                             .add_sub_option(CreateCommandOption::new(
                                 CommandOptionType::String,
                                 "duration",
-                                "Duration of punishment (i.e. 10m, 5h, 2d)")
+                                "Duration of punishment (i.e. 10m, 1h30m, 2w, perm, until 2026-12-01 18:00)")
                             )
+                            .add_sub_option(CreateCommandOption::new(
+                                CommandOptionType::Integer,
+                                "delete_days",
+                                "Days of messages to delete (defaults to 0)")
+                                .min_int_value(0)
+                                .max_int_value(7))
                             .add_sub_option(CreateCommandOption::new(
                                 CommandOptionType::String,
                                 "reason",
                                 "Reason for punishment") 
                                 .max_length(512))
+                            .add_sub_option(CreateCommandOption::new(
+                                CommandOptionType::Boolean,
+                                "notify",
+                                "Direct message the user about this (defaults to the server setting)"))
+                            .add_sub_option(CreateCommandOption::new(
+                                CommandOptionType::Attachment,
+                                "evidence",
+                                "Screenshot or file to keep with the punishment"))
+                            .add_sub_option(CreateCommandOption::new(
+                                CommandOptionType::String,
+                                "message_link",
+                                "Link to a message to keep with the punishment"))
+                            .add_sub_option(CreateCommandOption::new(
+                                CommandOptionType::String,
+                                "purge",
+                                "Delete the user's recent messages, a count like 50 or a length like 1h"))
                             )
                         )
                     // Remove
@@ -169,6 +474,10 @@ impl ModbotCmd {
                                 CommandOptionType::Boolean,
                                 "latest",
                                 "Remove the latest punishment for this user"))
+                            .add_sub_option(CreateCommandOption::new(
+                                CommandOptionType::Boolean,
+                                "notify",
+                                "Direct message the user about this (defaults to the server setting)"))
                     )
                     // Edit
                     .add_option(CreateCommandOption::new(
@@ -198,8 +507,20 @@ impl ModbotCmd {
                         .add_sub_option(CreateCommandOption::new(
                             CommandOptionType::String,
                             "duration",
-                            "Duration of punishment (i.e. 10m, 5h, 2d)")
+                            "Duration of punishment (i.e. 10m, 1h30m, 2w, perm, until 2026-12-01 18:00)")
                         )
+                        .add_sub_option(CreateCommandOption::new(
+                            CommandOptionType::Boolean,
+                            "notify",
+                            "Direct message the user about this (defaults to the server setting)"))
+                        .add_sub_option(CreateCommandOption::new(
+                            CommandOptionType::Attachment,
+                            "evidence",
+                            "Screenshot or file to add to the punishment"))
+                        .add_sub_option(CreateCommandOption::new(
+                            CommandOptionType::String,
+                            "message_link",
+                            "Link to a message to add to the punishment"))
                     ),
             ModbotCmd::RoleSet => 
                 CreateCommand::new("roleset")
@@ -219,7 +540,218 @@ impl ModbotCmd {
                         "Allow or disallow the command for this role") 
                         .required(true)
                 )
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::String,
+                        "capability",
+                        "The capability to change, all of them if omitted")
+                        .add_string_choice("All", "all")
+                        .add_string_choice("Fetch profiles", "fetch")
+                        .add_string_choice("Warn", "warn")
+                        .add_string_choice("Mute", "mute")
+                        .add_string_choice("Timeout", "timeout")
+                        .add_string_choice("Kick", "kick")
+                        .add_string_choice("Ban and softban", "ban")
+                        .add_string_choice("Edit punishments", "edit")
+                        .add_string_choice("Remove punishments", "remove")
+                        .add_string_choice("Set role permissions", "roleset")
+                ),
                     //Complete will be handled in run_command
+            ModbotCmd::Schedule =>
+                CreateCommand::new("schedule")
+                    .default_member_permissions(Permissions::ADMINISTRATOR)
+                    .add_context(InteractionContext::Guild)
+                    .add_integration_type(InstallationContext::Guild)
+                    .description("View and manage scheduled jobs")
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::SubCommand,
+                        "list",
+                        "List pending jobs for this server"))
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::SubCommand,
+                        "cancel",
+                        "Cancel a pending reminder (punishment expiries change with /punish edit)")
+                        .add_sub_option(CreateCommandOption::new(
+                            CommandOptionType::String,
+                            "id",
+                            "The ID of the job to cancel")
+                            .required(true)))
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::SubCommand,
+                        "remind",
+                        "Schedule a reminder in this channel")
+                        .add_sub_option(CreateCommandOption::new(
+                            CommandOptionType::String,
+                            "duration",
+                            "Time until the reminder (i.e. 10m, 1h30m, until 2026-12-01 18:00)")
+                            .required(true))
+                        .add_sub_option(CreateCommandOption::new(
+                            CommandOptionType::String,
+                            "message",
+                            "The reminder message")
+                            .required(true)
+                            .max_length(1000))),
+            ModbotCmd::ModConfig =>
+                CreateCommand::new("modconfig")
+                    .default_member_permissions(Permissions::ADMINISTRATOR)
+                    .add_context(InteractionContext::Guild)
+                    .add_integration_type(InstallationContext::Guild)
+                    .description("View or change Modbot settings for this server")
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::SubCommand,
+                        "view",
+                        "Show the current settings"))
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::SubCommand,
+                        "set",
+                        "Change one or more settings")
+                        .add_sub_option(CreateCommandOption::new(
+                            CommandOptionType::Channel,
+                            "log_channel",
+                            "Forum channel holding moderation profiles")
+                            .channel_types(vec![ChannelType::Forum]))
+                        .add_sub_option(CreateCommandOption::new(
+                            CommandOptionType::Channel,
+                            "notifier_channel",
+                            "Text channel holding private notification threads for punished users")
+                            .channel_types(vec![ChannelType::Text]))
+                        .add_sub_option(CreateCommandOption::new(
+                            CommandOptionType::Channel,
+                            "appeal_channel",
+                            "Text channel where appeals are queued for moderators")
+                            .channel_types(vec![ChannelType::Text]))
+                        .add_sub_option(CreateCommandOption::new(
+                            CommandOptionType::Role,
+                            "mute_role",
+                            "Role given to muted users"))
+                        .add_sub_option(CreateCommandOption::new(
+                            CommandOptionType::Boolean,
+                            "dm_default",
+                            "Direct message users about their punishments by default"))
+                        .add_sub_option(CreateCommandOption::new(
+                            CommandOptionType::String,
+                            "ban_color",
+                            "Profile color while banned (i.e. #FF0000)"))
+                        .add_sub_option(CreateCommandOption::new(
+                            CommandOptionType::String,
+                            "mute_color",
+                            "Profile color while muted (i.e. #FF9900)"))
+                        .add_sub_option(CreateCommandOption::new(
+                            CommandOptionType::String,
+                            "timeout_color",
+                            "Profile color while timed out (i.e. #FFE600)"))
+                        .add_sub_option(CreateCommandOption::new(
+                            CommandOptionType::String,
+                            "appeal",
+                            "Appeal instructions included in punishment notices, \"none\" to clear")
+                            .max_length(512))
+                        .add_sub_option(CreateCommandOption::new(
+                            CommandOptionType::Boolean,
+                            "archive",
+                            "Keep local copies of evidence files"))
+                        .add_sub_option(CreateCommandOption::new(
+                            CommandOptionType::Integer,
+                            "archive_max_size",
+                            "Largest evidence file to archive, in MB")
+                            .min_int_value(1)
+                            .max_int_value(500))
+                        .add_sub_option(CreateCommandOption::new(
+                            CommandOptionType::Integer,
+                            "archive_retention",
+                            "Days to keep archived evidence, 0 keeps it forever")
+                            .min_int_value(0)
+                            .max_int_value(3650))
+                        .add_sub_option(ModbotCmd::decay_option("warn_decay", "Days until a warning stops counting, 0 never"))
+                        .add_sub_option(ModbotCmd::decay_option("mute_decay", "Days after a mute ends until it stops counting, 0 never"))
+                        .add_sub_option(ModbotCmd::decay_option("timeout_decay", "Days after a timeout ends until it stops counting, 0 never"))
+                        .add_sub_option(ModbotCmd::decay_option("kick_decay", "Days until a kick stops counting, 0 never"))
+                        .add_sub_option(ModbotCmd::decay_option("softban_decay", "Days until a softban stops counting, 0 never"))
+                        .add_sub_option(ModbotCmd::decay_option("ban_decay", "Days after a ban ends until it stops counting, 0 never"))
+                        .add_sub_option(ModbotCmd::points_option("warn_points", "Points a new warning carries"))
+                        .add_sub_option(ModbotCmd::points_option("mute_points", "Points a new mute carries"))
+                        .add_sub_option(ModbotCmd::points_option("timeout_points", "Points a new timeout carries"))
+                        .add_sub_option(ModbotCmd::points_option("kick_points", "Points a new kick carries"))
+                        .add_sub_option(ModbotCmd::points_option("softban_points", "Points a new softban carries"))
+                        .add_sub_option(ModbotCmd::points_option("ban_points", "Points a new ban carries"))),
+            ModbotCmd::Escalation =>
+                CreateCommand::new("escalation")
+                    .default_member_permissions(Permissions::ADMINISTRATOR)
+                    .add_context(InteractionContext::Guild)
+                    .add_integration_type(InstallationContext::Guild)
+                    .description("Manage automatic escalation rules")
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::SubCommand,
+                        "add",
+                        "Punish automatically once a user reaches a number of punishments")
+                        .add_sub_option(ModbotCmd::punishment_choice("trigger", "Punishment type that is counted")
+                            .required(true))
+                        .add_sub_option(CreateCommandOption::new(
+                            CommandOptionType::Integer,
+                            "count",
+                            "Number of punishments of that type that triggers the rule")
+                            .min_int_value(1)
+                            .max_int_value(100)
+                            .required(true))
+                        .add_sub_option(ModbotCmd::punishment_choice("action", "Punishment applied automatically")
+                            .required(true))
+                        .add_sub_option(CreateCommandOption::new(
+                            CommandOptionType::String,
+                            "within",
+                            "Only count punishments this recent (i.e. 30d), the whole history if omitted"))
+                        .add_sub_option(CreateCommandOption::new(
+                            CommandOptionType::String,
+                            "duration",
+                            "Duration of the automatic punishment (i.e. 1h, 1d), permanent if omitted")))
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::SubCommand,
+                        "threshold",
+                        "Punish automatically once a user's point total reaches a threshold")
+                        .add_sub_option(CreateCommandOption::new(
+                            CommandOptionType::Integer,
+                            "points",
+                            "Point total that triggers the rule")
+                            .min_int_value(1)
+                            .max_int_value(1000)
+                            .required(true))
+                        .add_sub_option(ModbotCmd::punishment_choice("action", "Punishment applied automatically")
+                            .required(true))
+                        .add_sub_option(CreateCommandOption::new(
+                            CommandOptionType::String,
+                            "duration",
+                            "Duration of the automatic punishment (i.e. 1h, 1d), permanent if omitted")))
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::SubCommand,
+                        "list",
+                        "List escalation rules for this server"))
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::SubCommand,
+                        "remove",
+                        "Remove an escalation rule")
+                        .add_sub_option(CreateCommandOption::new(
+                            CommandOptionType::String,
+                            "id",
+                            "The ID of the rule, see /escalation list")
+                            .required(true))),
+            ModbotCmd::Evidence =>
+                CreateCommand::new("evidence")
+                    .default_member_permissions(Permissions::ADMINISTRATOR)
+                    .add_context(InteractionContext::Guild)
+                    .add_integration_type(InstallationContext::Guild)
+                    .description("Retrieve archived punishment evidence")
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::SubCommand,
+                        "get",
+                        "Re-upload the archived evidence of a punishment")
+                        .add_sub_option(CreateCommandOption::new(
+                            CommandOptionType::User,
+                            "user",
+                            "The user the punishment belongs to")
+                            .required(true)
+                            .set_autocomplete(true))
+                        .add_sub_option(CreateCommandOption::new(
+                            CommandOptionType::String,
+                            "case",
+                            "The ID of the punishment")
+                            .required(true))),
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,204 @@
+use regex::Regex;
+use serenity::model::{Timestamp, timestamp::InvalidTimestamp};
+
+/*
+Duration grammar accepted by every duration option:
+    perm | permanent
+    <n><unit>[<n><unit>...]     (i.e. 30s, 10m, 1h30m, 1d12h, 2w, 3mo)
+    until YYYY-MM-DD [HH:MM[:SS]]   (UTC)
+Anything else is rejected back to the moderator rather than treated as permanent.
+*/
+
+// Discord refuses timeouts longer than 28 days.
+pub const MAX_TIMEOUT: i64 = 28 * 24 * 60 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Length {
+    Permanent,
+    Timed(i64), //Seconds
+    Until(Timestamp),
+    Extend(i64), //Seconds added to an existing end, only produced by the profile Extend button
+}
+
+impl Length {
+    // End of a punishment starting at start, permanent punishments end at the epoch.
+    pub fn end_from(&self, start: Timestamp) -> Result<Timestamp, InvalidTimestamp> {
+        match self {
+            Length::Permanent => Ok(Timestamp::default()),
+            Length::Timed(seconds) | Length::Extend(seconds) => Timestamp::from_unix_timestamp(start.unix_timestamp() + seconds),
+            Length::Until(end) => Ok(*end),
+        }
+    }
+
+    // Seconds remaining from now, None when permanent.
+    pub fn seconds_from_now(&self) -> Option<i64> {
+        match self {
+            Length::Permanent => None,
+            Length::Timed(seconds) | Length::Extend(seconds) => Some(*seconds),
+            Length::Until(end) => Some(end.unix_timestamp() - Timestamp::now().unix_timestamp()),
+        }
+    }
+}
+
+pub fn parse_duration(input: &str) -> Result<Length, String> {
+    let duration = input.trim().to_lowercase();
+
+    if duration == "perm" || duration == "permanent" {
+        return Ok(Length::Permanent);
+    }
+
+    if let Some(date) = duration.strip_prefix("until") {
+        return parse_until(date.trim(), input);
+    }
+
+    let whole = Regex::new(r"^(\s*\d+\s*[a-z]+)+\s*$").map_err(|e| e.to_string())?;
+    if !whole.is_match(&duration) {
+        return Err(format!("Invalid duration `{}`, use values like 10m, 1h30m, 2w, perm or until 2026-12-01 18:00.", input));
+    }
+
+    let part = Regex::new(r"(\d+)\s*([a-z]+)").map_err(|e| e.to_string())?;
+    let mut total: i64 = 0;
+    for caps in part.captures_iter(&duration) {
+        let number = caps[1]
+            .parse::<i64>()
+            .map_err(|_| format!("Duration `{}` is too large.", input))?;
+        let unit = match &caps[2] {
+            "s" | "sec" | "secs" | "second" | "seconds" => 1,
+            "m" | "min" | "mins" | "minute" | "minutes" => 60,
+            "h" | "hr" | "hrs" | "hour" | "hours" => 60 * 60,
+            "d" | "day" | "days" => 60 * 60 * 24,
+            "w" | "wk" | "wks" | "week" | "weeks" => 60 * 60 * 24 * 7,
+            "mo" | "mon" | "month" | "months" => 60 * 60 * 24 * 30,
+            other => return Err(format!("Unknown unit `{}` in duration `{}`.", other, input)),
+        };
+        total = number
+            .checked_mul(unit)
+            .and_then(|seconds| total.checked_add(seconds))
+            .ok_or_else(|| format!("Duration `{}` is too large.", input))?;
+    }
+
+    if total <= 0 {
+        return Err(format!("Duration `{}` must be longer than zero.", input));
+    }
+    // Keep the end representable as a timestamp, which counts milliseconds and overflows well before i64::MAX seconds.
+    Timestamp::now()
+        .unix_timestamp()
+        .checked_add(total)
+        .filter(|end| *end <= i64::MAX / 1000)
+        .and_then(|end| Timestamp::from_unix_timestamp(end).ok())
+        .ok_or_else(|| format!("Duration `{}` is too large.", input))?;
+    Ok(Length::Timed(total))
+}
+
+// Compact form of a second count in the same units parse_duration reads, i.e. 1d12h.
+pub fn format_duration(seconds: i64) -> String {
+    const UNITS: [(&str, i64); 5] = [("w", 60 * 60 * 24 * 7), ("d", 60 * 60 * 24), ("h", 60 * 60), ("m", 60), ("s", 1)];
+    let mut remaining = seconds.max(0);
+    let mut formatted = String::new();
+    for (unit, size) in UNITS {
+        if remaining >= size {
+            formatted.push_str(&format!("{}{}", remaining / size, unit));
+            remaining %= size;
+        }
+    }
+    if formatted.is_empty() { "0s".to_string() } else { formatted }
+}
+
+fn parse_until(date: &str, input: &str) -> Result<Length, String> {
+    let re = Regex::new(r"^(\d{4}-\d{2}-\d{2})(?:[ t](\d{1,2}):(\d{2})(?::(\d{2}))?)?(?:\s*utc)?$").map_err(|e| e.to_string())?;
+    let caps = re
+        .captures(date)
+        .ok_or_else(|| format!("Invalid end time `{}`, use until YYYY-MM-DD HH:MM (UTC).", input))?;
+
+    let hour = caps.get(2).map_or(0, |h| h.as_str().parse::<u8>().unwrap_or(99));
+    let minute = caps.get(3).map_or("00", |m| m.as_str());
+    let second = caps.get(4).map_or("00", |s| s.as_str());
+    let end = Timestamp::parse(&format!("{}T{:02}:{}:{}Z", &caps[1], hour, minute, second))
+        .map_err(|_| format!("Invalid end time `{}`, use until YYYY-MM-DD HH:MM (UTC).", input))?;
+
+    if end <= Timestamp::now() {
+        return Err(format!("End time `{}` is already in the past.", input));
+    }
+    Ok(Length::Until(end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_compound_lengths() {
+        assert_eq!(parse_duration("30s"), Ok(Length::Timed(30)));
+        assert_eq!(parse_duration("10m"), Ok(Length::Timed(600)));
+        assert_eq!(parse_duration("1h30m"), Ok(Length::Timed(5400)));
+        assert_eq!(parse_duration("1d 12h"), Ok(Length::Timed(129600)));
+        assert_eq!(parse_duration("2w"), Ok(Length::Timed(1209600)));
+        assert_eq!(parse_duration("3mo"), Ok(Length::Timed(7776000)));
+        assert_eq!(parse_duration("2 Hours"), Ok(Length::Timed(7200)));
+    }
+
+    #[test]
+    fn parses_permanent() {
+        assert_eq!(parse_duration("perm"), Ok(Length::Permanent));
+        assert_eq!(parse_duration(" Permanent "), Ok(Length::Permanent));
+    }
+
+    #[test]
+    fn parses_end_times() {
+        let end = |input: &str| match parse_duration(input) {
+            Ok(Length::Until(end)) => end.unix_timestamp(),
+            other => panic!("{} parsed as {:?}", input, other),
+        };
+        assert_eq!(end("until 2099-12-01"), 4099766400);
+        assert_eq!(end("until 2099-12-01 18:00"), 4099831200);
+        assert_eq!(end("until 2099-12-01 18:00:30 UTC"), 4099831230);
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("soon").is_err());
+        assert!(parse_duration("10").is_err());
+        assert!(parse_duration("h10").is_err());
+        assert!(parse_duration("10x").unwrap_err().contains("Unknown unit"));
+        assert!(parse_duration("until tomorrow").unwrap_err().contains("Invalid end time"));
+        assert!(parse_duration("until 2099-13-01").unwrap_err().contains("Invalid end time"));
+        assert!(parse_duration("until 2099-12-01 25:00").unwrap_err().contains("Invalid end time"));
+    }
+
+    #[test]
+    fn rejects_zero() {
+        assert!(parse_duration("0m").unwrap_err().contains("longer than zero"));
+        assert!(parse_duration("0h0m").unwrap_err().contains("longer than zero"));
+    }
+
+    #[test]
+    fn rejects_overflow() {
+        assert!(parse_duration("99999999999999999999s").unwrap_err().contains("too large"));
+        assert!(parse_duration("9999999999999999w").unwrap_err().contains("too large"));
+        // Fits an i64, but the end is past the last representable timestamp.
+        assert!(parse_duration("9999999999999h").unwrap_err().contains("too large"));
+    }
+
+    #[test]
+    fn rejects_past_end_times() {
+        assert!(parse_duration("until 2000-01-01").unwrap_err().contains("in the past"));
+    }
+
+    #[test]
+    fn formats_lengths() {
+        assert_eq!(format_duration(0), "0s");
+        assert_eq!(format_duration(-5), "0s");
+        assert_eq!(format_duration(45), "45s");
+        assert_eq!(format_duration(5400), "1h30m");
+        assert_eq!(format_duration(129600), "1d12h");
+        assert_eq!(format_duration(1209600 + 61), "2w1m1s");
+    }
+
+    #[test]
+    fn formatted_lengths_parse_back() {
+        for seconds in [45, 5400, 129600, 1209661] {
+            assert_eq!(parse_duration(&format_duration(seconds)), Ok(Length::Timed(seconds)));
+        }
+    }
+}
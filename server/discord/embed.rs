@@ -1,13 +1,25 @@
 use serenity::{
     builder::{CreateEmbed, CreateEmbedFooter}, 
-    model::{ Timestamp, guild::PartialMember, user::User}, 
+    json,
+    model::{ Timestamp, channel::Embed, guild::PartialMember, user::User}, 
     utils::{FormattedTimestamp, FormattedTimestampStyle}
 };
-use crate::{db::PunishmentRecord, discord::commands::PunishmentType};
-use std::collections::BTreeMap;
+use crate::{db::{AutoModHit, GuildConfig, PunishmentRecord, point_total}, discord::commands::PunishmentType};
+use std::{cmp::Reverse, collections::BTreeMap};
+
+// Discord rejects embeds past 25 fields or 6000 characters, counted across every embed in a message.
+// History pages stay under that, less whatever the summary sent next to them by /fetchprofile takes.
+const FIELD_VALUE_LIMIT: usize = 1024;
+const EMBED_CHARACTERS: usize = 6000;
+const PAGE_FIELDS: usize = 10;
+const PAGE_CHARACTERS: usize = 4000;
+// Room left for the page title, "Punishment History (10/12)" and the like.
+const PAGE_TITLE: usize = 40;
+const AUTOMOD_RECENT: usize = 3;
 
 //Add a active flag to Profile to allow for fetches to go for the last punishment and set active punishment. Use temporary events to disable this flag if timed.
-pub async fn profembed(invodata: &User, data: &(User, Option<PartialMember>), punishments: &BTreeMap<String,PunishmentRecord>) -> CreateEmbed {
+pub async fn profembed(invodata: &User, data: &(User, Option<PartialMember>), punishments: &BTreeMap<String,PunishmentRecord>, automod: &[AutoModHit], quarantined: bool, config: &GuildConfig) -> CreateEmbed {
+    let colors = &config.colors;
     let mut footstring = format!("Moderator: {}", invodata.name);   
     let mut embed = CreateEmbed::default()
         .title(format!("User Profile"))
@@ -22,11 +34,11 @@ pub async fn profembed(invodata: &User, data: &(User, Option<PartialMember>), pu
             footstring.push_str("\nMember: ✅");     
             embed = embed.field("Join Date", FormattedTimestamp::new(member.joined_at.unwrap_or_default(), Some(FormattedTimestampStyle::ShortDateTime)).to_string(), true);
             if member.roles.len() > 0 {
-                embed = embed.field("Roles", member.roles
+                embed = embed.field("Roles", truncate(&member.roles
                         .iter()
                         .map(|r| format!("<@&{}>", r))
                         .collect::<Vec<String>>()
-                        .join(", "), 
+                        .join(", "), FIELD_VALUE_LIMIT), 
                         false);
             } 
             embed
@@ -39,51 +51,213 @@ pub async fn profembed(invodata: &User, data: &(User, Option<PartialMember>), pu
 
     
     embed = if punishments.len() > 0 {
-        let mut detailnames = vec![]; 
-        let mut punishdetails = vec![];
-        for (pid, record) in punishments.iter() {
-            detailnames.push(format!("{:?} (ID {})", record.punishment, pid));
-
-            if let Some(reason) = &record.reason {
-                punishdetails.push(format!("\n**Reason:** {}", reason));
+        let mut counts: BTreeMap<String, (usize, usize)> = BTreeMap::new(); //Active, Expired
+        for record in punishments.values() {
+            let count = counts.entry(format!("{:?}", record.punishment)).or_default();
+            if record.expired.is_some() {
+                count.1 += 1;
+            } else {
+                count.0 += 1;
             }
 
-            punishdetails.push(format!("\n**Period:** {} - {}\n**Moderator:** <@{}>\n\n",
-                FormattedTimestamp::new(record.punished_for.0, Some(FormattedTimestampStyle::ShortDateTime)).to_string(),
-                if record.punished_for.1 == Timestamp::default() {
-                    "Permanent".to_string()
-                } else {
-                    FormattedTimestamp::new(record.punished_for.1, Some(FormattedTimestampStyle::ShortDateTime)).to_string()
-                },
-                record.moderator,
-            ));
-
             if record.punished_for.1 == Timestamp::default() || record.punished_for.1 > Timestamp::now() {
                 embed = match record.punishment {
                     PunishmentType::Ban => {
                         footstring.push_str("  -  Banned: ✅");
-                        embed.color(0xFF0000) //Red
+                        embed.color(colors.ban)
                     }
                     PunishmentType::Mute => {
                         footstring.push_str("  -  Muted: ✅");
-                        embed.color(0xFF9900) //Orange
+                        embed.color(colors.mute)
                     }
                     PunishmentType::Timeout => {
                         footstring.push_str("  -  Timeout: ✅");
-                        embed.color(0xFFE600) //Yellow
+                        embed.color(colors.timeout)
                     },
                     _ => {embed}
                 };
             }
         }
-        for (name, detail) in detailnames.iter().rev().zip(punishdetails.iter().rev()) {
-            embed = embed.field(name, detail, false);
-        }
-        embed
+        embed.field("Points", point_total(punishments, config, Timestamp::now()).to_string(), true)
+            .field("Punishments", counts
+                .iter()
+                .map(|(punishment, (active, expired))| match expired {
+                    0 => format!("{} {}", active, punishment),
+                    _ => format!("{} {} ({} expired)", active, punishment, expired),
+                })
+                .collect::<Vec<String>>()
+                .join(", "),
+                false)
     } else {
         embed
     };
 
+    // Most recent hits only, the full list stays on the profile document.
+    if !automod.is_empty() {
+        let recent = automod
+            .iter()
+            .rev()
+            .take(AUTOMOD_RECENT)
+            .map(AutoModHit::describe)
+            .collect::<Vec<String>>()
+            .join("\n");
+        embed = embed.field(format!("AutoMod Hits ({})", automod.len()), truncate(&recent, FIELD_VALUE_LIMIT), false);
+    }
+
+    if quarantined {
+        footstring.push_str("  -  Quarantined: ✅");
+    }
+
     embed.footer(CreateEmbedFooter::new(footstring)
         .icon_url(invodata.avatar_url().unwrap_or_default()))
-}
\ No newline at end of file
+}
+
+// Punishment history newest first, split into pages that each fit in a single embed.
+// Reserved is what other embeds in the same message already take, see embed_length.
+pub fn history_pages(punishments: &BTreeMap<String,PunishmentRecord>, reserved: usize) -> Vec<CreateEmbed> {
+    let budget = PAGE_CHARACTERS.min(EMBED_CHARACTERS.saturating_sub(reserved + PAGE_TITLE));
+    let mut records = punishments.values().collect::<Vec<&PunishmentRecord>>();
+    records.sort_by_key(|record| Reverse(record.id.parse::<u16>().unwrap_or(0)));
+
+    let mut pages: Vec<Vec<(String, String)>> = vec![];
+    let mut length = 0;
+    for record in records {
+        let name = match record.expired {
+            Some(_) => format!("{:?} (ID {}) - Expired", record.punishment, record.id),
+            None => format!("{:?} (ID {})", record.punishment, record.id),
+        };
+        let detail = truncate(&record_detail(record), FIELD_VALUE_LIMIT);
+        let size = name.chars().count() + detail.chars().count();
+        match pages.last_mut() {
+            Some(page) if page.len() < PAGE_FIELDS && length + size <= budget => {
+                length += size;
+                page.push((name, detail));
+            }
+            _ => {
+                length = size;
+                pages.push(vec![(name, detail)]);
+            }
+        }
+    }
+
+    let total = pages.len();
+    pages
+        .into_iter()
+        .enumerate()
+        .map(|(i, fields)| CreateEmbed::default()
+            .title(format!("Punishment History ({}/{})", i + 1, total))
+            .fields(fields.into_iter().map(|(name, detail)| (name, detail, false))))
+        .collect()
+}
+
+// Characters Discord counts towards the message limit. The builder keeps its embed private, so it's read back through JSON.
+pub fn embed_length(embed: &CreateEmbed) -> usize {
+    let count = |text: &Option<String>| text.as_ref().map_or(0, |text| text.chars().count());
+    match json::to_value(embed).and_then(json::from_value::<Embed>) {
+        Ok(embed) => count(&embed.title)
+            + count(&embed.description)
+            + embed.author.map_or(0, |author| author.name.chars().count())
+            + embed.footer.map_or(0, |footer| footer.text.chars().count())
+            + embed.fields.iter().map(|field| field.name.chars().count() + field.value.chars().count()).sum::<usize>(),
+        // Leaves the page the room it had before summaries were measured.
+        Err(_) => EMBED_CHARACTERS - PAGE_CHARACTERS,
+    }
+}
+
+fn record_detail(record: &PunishmentRecord) -> String {
+    let mut detail = String::new();
+    if let Some(reason) = &record.reason {
+        detail.push_str(&format!("**Reason:** {}\n", reason));
+    }
+
+    detail.push_str(&format!("**{}:** {}\n**Moderator:** <@{}>",
+        if record.punishment.is_instant() { "Time" } else { "Period" },
+        period(record),
+        record.moderator,
+    ));
+
+    if let Some(points) = record.points {
+        detail.push_str(&format!("\n**Points:** {}", points));
+    }
+
+    if record.delete_days > 0 {
+        detail.push_str(&format!("\n**Messages deleted:** last {} day{}", record.delete_days, if record.delete_days == 1 { "" } else { "s" }));
+    }
+
+    if let Some(purged) = record.purged {
+        detail.push_str(&format!("\n**Messages purged:** {}", purged));
+    }
+
+    if let Some(automatic) = &record.automatic {
+        detail.push_str(&format!("\n**Automatic:** rule `{}` after ID {}", automatic.rule, automatic.trigger));
+    }
+
+    for evidence in &record.evidence {
+        detail.push_str(&format!("\n**Evidence:** {}", evidence.describe()));
+    }
+
+    // Subtext renders greyed out, which sets expired records apart in the history.
+    if let Some(expired) = record.expired {
+        detail = format!("-# **Expired:** {}\n{}",
+            FormattedTimestamp::new(expired, Some(FormattedTimestampStyle::ShortDateTime)),
+            detail.lines().map(|line| format!("-# {}", line)).collect::<Vec<String>>().join("\n"));
+    }
+    detail
+}
+
+fn truncate(text: &str, limit: usize) -> String {
+    match text.char_indices().nth(limit - 1) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+pub fn appeal_embed(user: &User, record: &PunishmentRecord, statement: &str) -> CreateEmbed {
+    let mut embed = CreateEmbed::default()
+        .title(format!("Appeal: {:?} (ID {})", record.punishment, record.id))
+        .description(statement)
+        .field("User", format!("<@{}>", user.id), true)
+        .field("Moderator", format!("<@{}>", record.moderator), true)
+        .thumbnail(user.avatar_url().unwrap_or_default())
+        .timestamp(Timestamp::now());
+    if let Some(reason) = &record.reason {
+        embed = embed.field("Reason", reason, false);
+    }
+    embed.field(if record.punishment.is_instant() { "Time" } else { "Period" }, period(record), false)
+}
+
+// Kicks and softbans happen at a single moment, so they show only their start.
+fn period(record: &PunishmentRecord) -> String {
+    let start = FormattedTimestamp::new(record.punished_for.0, Some(FormattedTimestampStyle::ShortDateTime)).to_string();
+    if record.punishment.is_instant() {
+        start
+    } else if record.is_permanent() {
+        format!("{} - Permanent", start)
+    } else {
+        format!("{} - {}", start, FormattedTimestamp::new(record.punished_for.1, Some(FormattedTimestampStyle::ShortDateTime)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serenity::builder::CreateEmbedAuthor;
+
+    #[test]
+    fn counts_every_text_part() {
+        let embed = CreateEmbed::default()
+            .title("Title")
+            .description("Description")
+            .author(CreateEmbedAuthor::new("Author"))
+            .footer(CreateEmbedFooter::new("Footer"))
+            .field("Name", "Value", false)
+            .thumbnail("https://example.com/avatar.png")
+            .timestamp(Timestamp::now());
+        assert_eq!(embed_length(&embed), 5 + 11 + 6 + 6 + 4 + 5);
+    }
+
+    #[test]
+    fn counts_characters_not_bytes() {
+        assert_eq!(embed_length(&CreateEmbed::default().description("✅❎")), 2);
+    }
+}
@@ -0,0 +1,50 @@
+use serenity::{
+    all::{CreateActionRow, CreateInputText, CreateModal, InputTextStyle},
+    model::id::{GuildId, MessageId},
+};
+
+/*
+The "Punish…" and "Punish author" context menus open a modal for the options /punish add would take.
+Its custom ID follows punish:<guild>:<user>[:<message>] so the submit knows who was right-clicked,
+and for "Punish author" which message snapshot to attach as evidence.
+*/
+
+pub fn punish_modal_id(guild: GuildId, user: i64, message: Option<MessageId>) -> String {
+    match message {
+        Some(message) => format!("punish:{}:{}:{}", guild, user, message),
+        None => format!("punish:{}:{}", guild, user),
+    }
+}
+
+pub fn parse_punish_modal_id(custom_id: &str) -> Option<(GuildId, i64, Option<MessageId>)> {
+    let mut parts = custom_id.strip_prefix("punish:")?.splitn(3, ':');
+    let guild = GuildId::new(parts.next()?.parse().ok()?);
+    let user = parts.next()?.parse().ok()?;
+    let message = match parts.next() {
+        Some(message) => Some(MessageId::new(message.parse().ok()?)),
+        None => None,
+    };
+    Some((guild, user, message))
+}
+
+pub fn punish_modal(guild: GuildId, user: i64, username: &str, message: Option<MessageId>) -> CreateModal {
+    // Modal titles are capped at 45 characters.
+    let title = format!("Punish {}", username.chars().take(38).collect::<String>());
+    CreateModal::new(punish_modal_id(guild, user, message), title)
+        .components(vec![
+            CreateActionRow::InputText(
+                CreateInputText::new(InputTextStyle::Short, "Type (warn, mute, timeout, kick, softban or ban)", "type")
+                    .required(true)
+                    .max_length(7),
+            ),
+            CreateActionRow::InputText(
+                CreateInputText::new(InputTextStyle::Short, "Duration (i.e. 10m, 1h30m, 2w, perm)", "duration")
+                    .required(false),
+            ),
+            CreateActionRow::InputText(
+                CreateInputText::new(InputTextStyle::Paragraph, "Reason", "reason")
+                    .required(false)
+                    .max_length(512),
+            ),
+        ])
+}
@@ -1,5 +1,12 @@
 pub mod commands;
+pub mod duration;
 pub mod client;
 pub mod embed;
 pub mod thread;
-pub mod punishment;
\ No newline at end of file
+pub mod punishment;
+pub mod permission;
+pub mod responder;
+pub mod appeal;
+pub mod profile;
+pub mod menu;
+pub mod purge;
\ No newline at end of file
@@ -0,0 +1,57 @@
+use crate::{
+    db::RolePermission,
+    discord::commands::{Capability, CommandOptions, PunishmentAction},
+};
+
+use serenity::model::{guild::Member, id::RoleId};
+
+/*
+Role capabilities are enforced here rather than by Discord command overrides. Editing overrides needs a user's
+OAuth2 Bearer token, the bot token is always refused, so commands ship with Administrator as their default permission
+and server admins grant roles access under Server Settings > Integrations. Every command is then checked against RolePermission.
+*/
+
+// None means the command is left to Administrators.
+pub fn required_capability(name: &str, opts: &CommandOptions) -> Option<Capability> {
+    match name {
+        "fetchprofile" | "evidence" => Some(Capability::Fetch),
+        "punish" => match opts.action {
+            Some(PunishmentAction::Add) => opts.punishment.as_ref().map(Capability::for_punishment),
+            Some(PunishmentAction::Edit) => Some(Capability::Edit),
+            Some(PunishmentAction::Remove) => Some(Capability::Remove),
+            None => None,
+        },
+        "roleset" => Some(Capability::RoleSet),
+        _ => None,
+    }
+}
+
+pub fn authorized(roleperms: Option<&Vec<RolePermission>>, member: &Member, capability: Option<Capability>) -> bool {
+    if member.permissions.is_some_and(|p| p.administrator()) {
+        return true;
+    }
+    match (roleperms, capability) {
+        (Some(roleperms), Some(capability)) => roleperms
+            .iter()
+            .any(|roleperm| member.roles.contains(&RoleId::new(roleperm.role_id as u64)) && roleperm.has(capability)),
+        _ => false,
+    }
+}
+
+pub fn permission_table(roleperms: &[RolePermission]) -> String {
+    if roleperms.is_empty() {
+        return "No role permissions set, only Administrators can use Modbot commands.".to_string();
+    }
+    roleperms
+        .iter()
+        .map(|roleperm| {
+            let capabilities = Capability::ALL
+                .iter()
+                .filter(|capability| roleperm.has(**capability))
+                .map(|capability| format!("{:?}", capability))
+                .collect::<Vec<String>>();
+            format!("<@&{}>: {}", roleperm.role_id, if capabilities.is_empty() { "❎".to_string() } else { capabilities.join(", ") })
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
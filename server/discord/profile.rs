@@ -0,0 +1,163 @@
+use serenity::{
+    all::{ButtonStyle, CreateActionRow, CreateButton, CreateInputText, CreateModal, InputTextStyle},
+    model::id::GuildId,
+};
+
+/*
+Moderation buttons on profile posts in the log forum, custom IDs follow profile:<action>:<guild>:<user>.
+Actions that need input open a modal, whose submit action carries the same guild and user.
+*/
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProfileAction {
+    RemoveLatest,
+    EditReason,
+    EditReasonSubmit,
+    Extend,
+    ExtendSubmit,
+    AddNote,
+    AddNoteSubmit,
+}
+
+impl ProfileAction {
+    fn name(&self) -> &'static str {
+        match self {
+            ProfileAction::RemoveLatest => "removelatest",
+            ProfileAction::EditReason => "editreason",
+            ProfileAction::EditReasonSubmit => "editreasonsubmit",
+            ProfileAction::Extend => "extend",
+            ProfileAction::ExtendSubmit => "extendsubmit",
+            ProfileAction::AddNote => "addnote",
+            ProfileAction::AddNoteSubmit => "addnotesubmit",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "removelatest" => Some(ProfileAction::RemoveLatest),
+            "editreason" => Some(ProfileAction::EditReason),
+            "editreasonsubmit" => Some(ProfileAction::EditReasonSubmit),
+            "extend" => Some(ProfileAction::Extend),
+            "extendsubmit" => Some(ProfileAction::ExtendSubmit),
+            "addnote" => Some(ProfileAction::AddNote),
+            "addnotesubmit" => Some(ProfileAction::AddNoteSubmit),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ProfileId {
+    pub action: ProfileAction,
+    pub guild: GuildId,
+    pub user: i64,
+}
+
+impl ProfileId {
+    pub fn new(action: ProfileAction, guild: GuildId, user: i64) -> Self {
+        ProfileId { action, guild, user }
+    }
+
+    pub fn with(&self, action: ProfileAction) -> Self {
+        ProfileId { action, ..self.clone() }
+    }
+
+    pub fn custom_id(&self) -> String {
+        format!("profile:{}:{}:{}", self.action.name(), self.guild, self.user)
+    }
+
+    pub fn parse(custom_id: &str) -> Option<Self> {
+        let mut parts = custom_id.strip_prefix("profile:")?.splitn(3, ':');
+        Some(ProfileId {
+            action: ProfileAction::from_name(parts.next()?)?,
+            guild: GuildId::new(parts.next()?.parse().ok()?),
+            user: parts.next()?.parse().ok()?,
+        })
+    }
+}
+
+pub fn profile_buttons(guild: GuildId, user: i64) -> CreateActionRow {
+    let button = |action: ProfileAction, label: &str, style: ButtonStyle| {
+        CreateButton::new(ProfileId::new(action, guild, user).custom_id())
+            .label(label)
+            .style(style)
+    };
+    CreateActionRow::Buttons(vec![
+        button(ProfileAction::RemoveLatest, "Remove latest", ButtonStyle::Danger),
+        button(ProfileAction::EditReason, "Edit reason", ButtonStyle::Secondary),
+        button(ProfileAction::Extend, "Extend", ButtonStyle::Secondary),
+        button(ProfileAction::AddNote, "Add note", ButtonStyle::Primary),
+    ])
+}
+
+// Leaving the ID empty targets the latest punishment, like the latest option on /punish.
+fn id_input() -> CreateActionRow {
+    CreateActionRow::InputText(
+        CreateInputText::new(InputTextStyle::Short, "Punishment ID (empty for latest)", "id")
+            .required(false)
+            .max_length(5),
+    )
+}
+
+pub fn edit_reason_modal(id: &ProfileId) -> CreateModal {
+    CreateModal::new(id.with(ProfileAction::EditReasonSubmit).custom_id(), "Edit reason")
+        .components(vec![
+            id_input(),
+            CreateActionRow::InputText(
+                CreateInputText::new(InputTextStyle::Paragraph, "New reason", "reason")
+                    .max_length(512)
+                    .required(true),
+            ),
+        ])
+}
+
+pub fn extend_modal(id: &ProfileId) -> CreateModal {
+    CreateModal::new(id.with(ProfileAction::ExtendSubmit).custom_id(), "Extend punishment")
+        .components(vec![
+            id_input(),
+            CreateActionRow::InputText(
+                CreateInputText::new(InputTextStyle::Short, "Add time (i.e. 1d, 12h) or perm", "duration")
+                    .required(true),
+            ),
+        ])
+}
+
+pub fn note_modal(id: &ProfileId) -> CreateModal {
+    CreateModal::new(id.with(ProfileAction::AddNoteSubmit).custom_id(), "Add note")
+        .components(vec![CreateActionRow::InputText(
+            CreateInputText::new(InputTextStyle::Paragraph, "Note", "note")
+                .max_length(1000)
+                .required(true),
+        )])
+}
+
+/*
+/fetchprofile pages through the history with Prev and Next, custom IDs follow profilepage:<guild>:<user>:<page>.
+The page each button leads to is in its ID, so the response needs no state.
+*/
+
+pub fn page_id(guild: GuildId, user: i64, page: usize) -> String {
+    format!("profilepage:{}:{}:{}", guild, user, page)
+}
+
+pub fn parse_page_id(custom_id: &str) -> Option<(GuildId, i64, usize)> {
+    let mut parts = custom_id.strip_prefix("profilepage:")?.splitn(3, ':');
+    Some((
+        GuildId::new(parts.next()?.parse().ok()?),
+        parts.next()?.parse().ok()?,
+        parts.next()?.parse().ok()?,
+    ))
+}
+
+pub fn page_buttons(guild: GuildId, user: i64, page: usize, pages: usize) -> CreateActionRow {
+    CreateActionRow::Buttons(vec![
+        CreateButton::new(page_id(guild, user, page.saturating_sub(1)))
+            .label("Prev")
+            .style(ButtonStyle::Secondary)
+            .disabled(page == 0),
+        CreateButton::new(page_id(guild, user, page + 1))
+            .label("Next")
+            .style(ButtonStyle::Secondary)
+            .disabled(page + 1 >= pages),
+    ])
+}
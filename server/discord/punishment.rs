@@ -1,31 +1,49 @@
-use crate::{db::PunishmentRecord, discord::commands::PunishmentType};
+use crate::{db::{GuildConfig, PunishmentRecord}, discord::commands::PunishmentType};
 
 use serenity::{
-    all::User,
-    model::id::GuildId, prelude::*,
+    all::{CreateActionRow, CreateMessage, User},
+    model::id::{GuildId, UserId}, prelude::*,
+    utils::{FormattedTimestamp, FormattedTimestampStyle},
 };
 
+#[derive(Debug, Clone, Copy)]
+pub enum Notice {
+    Added,
+    Edited,
+    Removed,
+    Expired,
+    AppealDenied,
+}
+
 
 
-pub async fn apply_punishment(ctx: &Context, guild: GuildId, record: &PunishmentRecord, target: &User) -> Result<(), SerenityError> {
+pub async fn apply_punishment(ctx: &Context, guild: GuildId, record: &PunishmentRecord, target: &User, config: &GuildConfig) -> Result<(), SerenityError> {
     match record.punishment {
 
         PunishmentType::Ban => {
-            guild.ban(&ctx.http, target, 0).await?;
+            guild.ban(&ctx.http, target, record.delete_days).await?;
         }
         PunishmentType::Mute => {
-            if let Some(role) = guild.roles(&ctx.http).await?.values().find(|role| role.name == "Muted") {
+            if let Some(role) = config.mute_role {
                 guild.member(&ctx.http, target).await?
-                    .add_role(&ctx.http, role.id)
+                    .add_role(&ctx.http, role)
                     .await?;
             } else {
-                return Err(SerenityError::Other("Mute role not found."));
+                return Err(SerenityError::Other("Mute role not configured."));
             }
         }
         PunishmentType::Timeout => {
             guild.member(&ctx.http, target).await?
                 .disable_communication_until_datetime(&ctx.http, record.punished_for.1).await?;
         }
+        PunishmentType::Kick => {
+            guild.kick(&ctx.http, target).await?;
+        }
+        // The ban is only there to delete messages, so it's lifted right away.
+        PunishmentType::Softban => {
+            guild.ban(&ctx.http, target, record.delete_days).await?;
+            guild.unban(&ctx.http, target).await?;
+        }
         PunishmentType::Warn => {
         
         }
@@ -33,27 +51,68 @@ pub async fn apply_punishment(ctx: &Context, guild: GuildId, record: &Punishment
     Ok(())
 }
 
-pub async fn remove_punishment(ctx: &Context, guild: GuildId, record: &PunishmentRecord, target: &User) -> Result<(), SerenityError> {
+pub async fn remove_punishment(ctx: &Context, guild: GuildId, record: &PunishmentRecord, target: &User, config: &GuildConfig) -> Result<(), SerenityError> {
     match record.punishment {
         PunishmentType::Ban => {
             guild.unban(&ctx.http, target).await?;
         }
         PunishmentType::Mute => {
-            if let Some(role) = guild.roles(&ctx.http).await?.values().find(|role| role.name == "Muted") {
+            if let Some(role) = config.mute_role {
                 guild.member(&ctx.http, target).await?
-                    .remove_role(&ctx.http, role.id)
+                    .remove_role(&ctx.http, role)
                     .await?;
             } else {
-                return Err(SerenityError::Other("Mute role not found."));
+                return Err(SerenityError::Other("Mute role not configured."));
             }
         }
         PunishmentType::Timeout => {
             guild.member(&ctx.http, target).await?
                 .enable_communication(&ctx.http).await?;
+        }
+        // Kicks and softbans are over once they happened, there is nothing to lift.
+        PunishmentType::Kick | PunishmentType::Softban => {
+
         }
         PunishmentType::Warn => {
         
         }
     }
     Ok(())
-}
\ No newline at end of file
+}
+
+// Shared by the notifier thread and direct messages, so both always say the same thing.
+pub fn notice_message(notice: Notice, record: &PunishmentRecord, appeal: Option<&str>) -> String {
+    let (ptype, id) = (&record.punishment, &record.id);
+    let mut message = match notice {
+        Notice::Added => format!("You have received {:?} (ID {}).", ptype, id),
+        Notice::Edited => format!("A moderator has edited your {:?} (ID {}).", ptype, id),
+        Notice::Removed => format!("A moderator has removed your {:?} (ID {}).", ptype, id),
+        Notice::Expired => format!("Your {:?} (ID {}) has expired.", ptype, id),
+        Notice::AppealDenied => format!("Your appeal of {:?} (ID {}) was denied.", ptype, id),
+    };
+    if let Some(reason) = &record.reason {
+        message.push_str(&format!("\n**Reason:** {}", reason));
+    }
+    if matches!(notice, Notice::Added | Notice::Edited) && !matches!(record.punishment, PunishmentType::Warn) && !record.punishment.is_instant() {
+        let ends = if record.is_permanent() {
+            "Permanent".to_string()
+        } else {
+            FormattedTimestamp::new(record.punished_for.1, Some(FormattedTimestampStyle::LongDateTime)).to_string()
+        };
+        message.push_str(&format!("\n**Ends:** {}", ends));
+    }
+    if let (Some(appeal), Notice::Added | Notice::Edited) = (appeal, notice) {
+        message.push_str(&format!("\n**Appeal:** {}", appeal));
+    }
+    message
+}
+
+pub async fn dm_notice(ctx: &Context, guild: GuildId, target: UserId, message: &str, components: Vec<CreateActionRow>) -> Result<(), SerenityError> {
+    let guildname = guild.name(&ctx.cache).unwrap_or_else(|| guild.to_string());
+    target
+        .direct_message(&ctx.http, CreateMessage::new()
+            .content(format!("**{}**\n{}", guildname, message))
+            .components(components))
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,203 @@
+use crate::discord::duration::{parse_duration, Length};
+use std::collections::BTreeMap;
+use serenity::{
+    all::{ChannelType, GetMessages},
+    model::{Timestamp, id::{ChannelId, GuildId, MessageId, UserId}},
+    prelude::*,
+};
+
+/*
+Purges delete a user's recent messages as part of /punish add, either the latest count of them or everything inside a window.
+Text channels, active threads and the most recently archived threads of each channel are scanned newest first, messages younger than 14 days are bulk deleted
+and older ones one at a time. Scanning runs in ClientHandler once DB_Handler has recorded the punishment,
+so a punishment that fails deletes nothing, and the count is then written to the record and appended to the response.
+*/
+
+pub const MAX_PURGE: usize = 500;
+// Messages read per channel at most, so a purge over a busy guild still finishes.
+const SCAN_LIMIT: usize = 1000;
+// Archived threads read per channel and visibility, the most recently archived first.
+const ARCHIVED_LIMIT: u64 = 50;
+// Discord refuses bulk deletes of messages older than this.
+const BULK_AGE: i64 = 14 * 24 * 60 * 60;
+
+#[derive(Debug, Clone, Copy)]
+pub enum PurgeScope {
+    Count(usize),
+    Since(Timestamp),
+}
+
+impl PurgeScope {
+    // A plain number is a message count, anything else is read as a length like 1h.
+    pub fn parse(input: &str) -> Result<Self, String> {
+        if let Ok(count) = input.trim().parse::<usize>() {
+            return match count {
+                1..=MAX_PURGE => Ok(PurgeScope::Count(count)),
+                _ => Err(format!("Purges take between 1 and {} messages.", MAX_PURGE)),
+            };
+        }
+        match parse_duration(input)? {
+            Length::Timed(seconds) => Timestamp::from_unix_timestamp(Timestamp::now().unix_timestamp() - seconds)
+                .map(PurgeScope::Since)
+                .map_err(|e| e.to_string()),
+            _ => Err("The purge must be a message count like 50 or a length like 1h.".to_string()),
+        }
+    }
+
+    fn includes(&self, message: MessageId) -> bool {
+        match self {
+            PurgeScope::Count(_) => true,
+            PurgeScope::Since(since) => message.created_at().unix_timestamp() >= since.unix_timestamp(),
+        }
+    }
+}
+
+// Returns how many messages were deleted.
+pub async fn purge_messages(ctx: &Context, guild: GuildId, user: UserId, scope: PurgeScope) -> Result<usize, SerenityError> {
+    let guildchannels = guild.channels(&ctx.http).await?;
+    let mut channels = guildchannels
+        .values()
+        .filter(|channel| matches!(channel.kind, ChannelType::Text | ChannelType::News | ChannelType::Voice | ChannelType::Stage))
+        .map(|channel| channel.id)
+        .collect::<Vec<ChannelId>>();
+    match guild.get_active_threads(&ctx.http).await {
+        Ok(threads) => channels.extend(threads.threads.into_iter().map(|thread| thread.id)),
+        Err(e) => eprintln!("Error fetching active threads in Purge: {}", e),
+    }
+    // Forum posts are threads too, so forums are only looked at for their archived ones.
+    for parent in guildchannels.values().filter(|channel| matches!(channel.kind, ChannelType::Text | ChannelType::News | ChannelType::Forum)) {
+        channels.extend(archived_threads(ctx, parent.id, scope).await);
+    }
+
+    let mut found = vec![];
+    for channel in channels {
+        match scan_channel(ctx, channel, user, scope).await {
+            Ok(messages) => found.extend(messages.into_iter().map(|message| (channel, message))),
+            // Channels the bot can't read are skipped, the rest of the guild is still purged.
+            Err(e) => eprintln!("Error reading channel {} in Purge: {}", channel, e),
+        }
+    }
+    // Snowflakes sort by time, so the newest messages across all channels come first.
+    found.sort_by_key(|(_, message)| std::cmp::Reverse(*message));
+    if let PurgeScope::Count(count) = scope {
+        found.truncate(count);
+    }
+
+    let bulk_since = Timestamp::now().unix_timestamp() - BULK_AGE;
+    let mut deleted = 0;
+    let mut by_channel = BTreeMap::<ChannelId, Vec<MessageId>>::new();
+    for (channel, message) in found {
+        by_channel.entry(channel).or_default().push(message);
+    }
+    for (channel, messages) in by_channel {
+        let (bulk, single): (Vec<MessageId>, Vec<MessageId>) = messages
+            .into_iter()
+            .partition(|message| message.created_at().unix_timestamp() > bulk_since);
+        for chunk in bulk.chunks(100) {
+            match channel.delete_messages(&ctx.http, chunk).await {
+                Ok(_) => deleted += chunk.len(),
+                Err(e) => eprintln!("Error bulk deleting in channel {} in Purge: {}", channel, e),
+            }
+        }
+        for message in single {
+            match channel.delete_message(&ctx.http, message).await {
+                Ok(_) => deleted += 1,
+                Err(e) => eprintln!("Error deleting message {} in Purge: {}", message, e),
+            }
+        }
+    }
+    Ok(deleted)
+}
+
+// Private threads need Manage Threads, without it only the public ones are found.
+async fn archived_threads(ctx: &Context, parent: ChannelId, scope: PurgeScope) -> Vec<ChannelId> {
+    let mut threads = vec![];
+    for private in [false, true] {
+        let archived = if private {
+            parent.get_archived_private_threads(&ctx.http, None, Some(ARCHIVED_LIMIT)).await
+        } else {
+            parent.get_archived_public_threads(&ctx.http, None, Some(ARCHIVED_LIMIT)).await
+        };
+        match archived {
+            Ok(archived) => threads.extend(archived.threads
+                .into_iter()
+                // A thread whose last message is older than the window holds nothing to purge.
+                .filter(|thread| thread.last_message_id.is_none_or(|last| scope.includes(last)))
+                .map(|thread| thread.id)),
+            Err(e) => eprintln!("Error fetching archived threads of channel {} in Purge: {}", parent, e),
+        }
+    }
+    threads
+}
+
+async fn scan_channel(ctx: &Context, channel: ChannelId, user: UserId, scope: PurgeScope) -> Result<Vec<MessageId>, SerenityError> {
+    let mut matched = vec![];
+    let mut before: Option<MessageId> = None;
+    let mut scanned = 0;
+    while scanned < SCAN_LIMIT {
+        let mut request = GetMessages::new().limit(100);
+        if let Some(before) = before {
+            request = request.before(before);
+        }
+        let page = channel.messages(&ctx.http, request).await?;
+        let Some(last) = page.last() else {
+            break;
+        };
+        before = Some(last.id);
+        scanned += page.len();
+        let reached = !scope.includes(last.id);
+        matched.extend(page
+            .iter()
+            .filter(|message| message.author.id == user && scope.includes(message.id))
+            .map(|message| message.id));
+        // One channel can't contribute more than the whole count, and pages past the window hold nothing newer.
+        if reached || page.len() < 100 || matches!(scope, PurgeScope::Count(count) if matched.len() >= count) {
+            break;
+        }
+    }
+    Ok(matched)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_numbers_are_counts() {
+        assert!(matches!(PurgeScope::parse("50"), Ok(PurgeScope::Count(50))));
+        assert!(matches!(PurgeScope::parse(" 1 "), Ok(PurgeScope::Count(1))));
+        assert!(matches!(PurgeScope::parse("500"), Ok(PurgeScope::Count(500))));
+    }
+
+    #[test]
+    fn rejects_counts_out_of_range() {
+        assert!(PurgeScope::parse("0").is_err());
+        assert!(PurgeScope::parse("501").is_err());
+    }
+
+    #[test]
+    fn lengths_are_windows() {
+        let before = Timestamp::now().unix_timestamp();
+        let Ok(PurgeScope::Since(since)) = PurgeScope::parse("1h") else {
+            panic!("1h should parse as a window");
+        };
+        let after = Timestamp::now().unix_timestamp();
+        assert!((before - 3600..=after - 3600).contains(&since.unix_timestamp()));
+    }
+
+    #[test]
+    fn rejects_lengths_without_an_end() {
+        assert!(PurgeScope::parse("perm").is_err());
+        assert!(PurgeScope::parse("soon").is_err());
+    }
+
+    #[test]
+    fn windows_include_only_newer_messages() {
+        let scope = PurgeScope::Since(Timestamp::from_unix_timestamp(1_700_000_000).unwrap());
+        // Snowflakes carry their creation time in milliseconds since the Discord epoch in the top bits.
+        let at = |seconds: u64| MessageId::new((seconds * 1000 - 1_420_070_400_000) << 22);
+        assert!(scope.includes(at(1_700_000_001)));
+        assert!(!scope.includes(at(1_699_999_999)));
+        assert!(PurgeScope::Count(5).includes(at(1_600_000_000)));
+    }
+}
@@ -0,0 +1,71 @@
+use serenity::{
+    all::{CommandInteraction, ComponentInteraction, CreateInteractionResponse, EditInteractionResponse, Message, ModalInteraction, User},
+    http::CacheHttp,
+    model::id::{ChannelId, MessageId},
+    prelude::*,
+};
+
+/*
+The interaction a DB request answers. Punishments can be issued from slash commands,
+from buttons posted by the bot, or from the modals those buttons open.
+Component interactions carry the whole source message, so they are boxed to keep requests small.
+*/
+pub enum Responder {
+    Command(CommandInteraction),
+    Component(Box<ComponentInteraction>),
+    Modal(ModalInteraction),
+}
+
+impl Responder {
+    pub async fn create_response(&self, cache_http: impl CacheHttp, builder: CreateInteractionResponse) -> Result<(), SerenityError> {
+        match self {
+            Responder::Command(command) => command.create_response(cache_http, builder).await,
+            Responder::Component(component) => component.create_response(cache_http, builder).await,
+            Responder::Modal(modal) => modal.create_response(cache_http, builder).await,
+        }
+    }
+
+    pub async fn edit_response(&self, cache_http: impl CacheHttp, builder: EditInteractionResponse) -> Result<Message, SerenityError> {
+        match self {
+            Responder::Command(command) => command.edit_response(cache_http, builder).await,
+            Responder::Component(component) => component.edit_response(cache_http, builder).await,
+            Responder::Modal(modal) => modal.edit_response(cache_http, builder).await,
+        }
+    }
+
+    // Who triggered the interaction.
+    pub fn user(&self) -> &User {
+        match self {
+            Responder::Command(command) => &command.user,
+            Responder::Component(component) => &component.user,
+            Responder::Modal(modal) => &modal.user,
+        }
+    }
+
+    // Message holding the button this interaction started from, if any.
+    pub fn source_message(&self) -> Option<(ChannelId, MessageId)> {
+        match self {
+            Responder::Command(_) => None,
+            Responder::Component(component) => Some((component.channel_id, component.message.id)),
+            Responder::Modal(modal) => modal.message.as_ref().map(|message| (modal.channel_id, message.id)),
+        }
+    }
+}
+
+impl From<CommandInteraction> for Responder {
+    fn from(command: CommandInteraction) -> Self {
+        Responder::Command(command)
+    }
+}
+
+impl From<ComponentInteraction> for Responder {
+    fn from(component: ComponentInteraction) -> Self {
+        Responder::Component(Box::new(component))
+    }
+}
+
+impl From<ModalInteraction> for Responder {
+    fn from(modal: ModalInteraction) -> Self {
+        Responder::Modal(modal)
+    }
+}
@@ -1,7 +1,6 @@
 use serenity::{
-    all::{CreateEmbed, CreateForumPost, CreateMessage, EditMessage},
-    builder::GetMessages,
-    model::id::ChannelId,
+    all::{AutoArchiveDuration, ChannelType, CreateActionRow, CreateEmbed, CreateForumPost, CreateMessage, CreateThread, EditMessage},
+    model::id::{ChannelId, MessageId, UserId},
     prelude::*,
 };
 
@@ -41,21 +40,66 @@ pub async fn active_thread_search(guild: &GuildId,ctx: &Context,threadname: Stri
 }
 */
 
-pub async fn update_thread_post(ctx: &Context,thread: &ChannelId,edit: CreateEmbed) -> Result<(), SerenityError> {
-    //Finds the first message in the thread and edit it with new embed. Include additional adding if it exceeds 5 punishments.
-    let messages = thread.messages(&ctx.http, GetMessages::new().limit(1)).await?;
-    if let Some(mut message) = messages.into_iter().next() {
-        message
-            .edit(&ctx.http, EditMessage::new()
-            .embed(edit)).await?;
+pub async fn update_thread_post(ctx: &Context,thread: &ChannelId,edit: CreateEmbed, components: Vec<CreateActionRow>) -> Result<(), SerenityError> {
+    //The starter message of a forum post shares the thread's ID and holds the summary, history pages and notes follow it.
+    thread
+        .edit_message(&ctx.http, MessageId::new(thread.get()), EditMessage::new()
+        .embed(edit)
+        .components(components)).await?;
+    Ok(())
+}
+
+// History pages are edited in place, so their message IDs are kept on the profile. Returns the IDs now in use.
+pub async fn sync_history_posts(ctx: &Context, thread: &ChannelId, pages: Vec<CreateEmbed>, posts: &[MessageId]) -> Result<Vec<MessageId>, SerenityError> {
+    let mut kept = vec![];
+    for (i, page) in pages.into_iter().enumerate() {
+        let edited = match posts.get(i) {
+            Some(post) => thread
+                .edit_message(&ctx.http, *post, EditMessage::new().embed(page.clone()))
+                .await
+                .map(|message| message.id)
+                .ok(),
+            None => None,
+        };
+        // A page deleted by hand is posted again rather than failing the whole update.
+        let id = match edited {
+            Some(id) => id,
+            None => thread.send_message(&ctx.http, CreateMessage::new().embed(page)).await?.id,
+        };
+        kept.push(id);
     }
+    for post in posts.iter().filter(|post| !kept.contains(post)) {
+        if let Err(e) = thread.delete_message(&ctx.http, *post).await {
+            eprintln!("Error deleting unused history page in History Sync: {}", e);
+        }
+    }
+    Ok(kept)
+}
+
+pub async fn note_thread(ctx: &Context, thread: &ChannelId, note: String) -> Result<(), SerenityError> {
+    thread.say(&ctx.http, note).await?;
     Ok(())
 }
 
-pub async fn create_user_profile(log: &ChannelId,ctx: &Context, embed: CreateEmbed, userid: i64) -> Result<ChannelId, SerenityError> {
+pub async fn create_user_profile(log: &ChannelId,ctx: &Context, embed: CreateEmbed, userid: i64, components: Vec<CreateActionRow>) -> Result<ChannelId, SerenityError> {
     Ok(log.create_forum_post(&ctx.http,CreateForumPost::new(userid.to_string(),
     CreateMessage::new()
                 .embed(embed)
+                .components(components)
         )
     ).await?.id)
-}
\ No newline at end of file
+}
+
+// Private threads are only visible to their members and to roles with Manage Threads, which keeps each notice between the user and the moderators.
+pub async fn create_notify_thread(notifier: &ChannelId, ctx: &Context, userid: i64, members: &[UserId]) -> Result<ChannelId, SerenityError> {
+    let thread = notifier
+        .create_thread(&ctx.http, CreateThread::new(userid.to_string())
+            .kind(ChannelType::PrivateThread)
+            .invitable(false)
+            .auto_archive_duration(AutoArchiveDuration::OneWeek)
+        ).await?;
+    for member in members {
+        thread.id.add_thread_member(&ctx.http, *member).await?;
+    }
+    Ok(thread.id)
+}
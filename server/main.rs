@@ -19,10 +19,13 @@ async fn main() {
     | GatewayIntents::AUTO_MODERATION_EXECUTION;
 
     let (sender, receiver) = tokio::sync::mpsc::channel(100);
-    let chandle = ClientHandler::new(sender.clone());
+    let punished = db::PunishedSet::default();
+    let own_actions = db::OwnActions::default();
+    let roles = db::RoleCache::default();
+    let chandle = ClientHandler::new(sender, punished.clone(), own_actions.clone(), roles.clone());
     
     tokio::spawn(async move {
-        let mut dbconnection = db::DBHandler::new(receiver, sender);
+        let mut dbconnection = db::DBHandler::new(receiver, punished, own_actions, roles);
         dbconnection.process_requests().await;
     });
 